@@ -0,0 +1,136 @@
+use crate::state_handler;
+use crate::types::*;
+use crate::user_management;
+
+const TRENDING_WINDOW: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days in nanoseconds
+const MAX_TRACK_TITLE_LENGTH: usize = 200;
+
+pub fn validate_music_info(music_info: &MusicInfo) -> Result<(), String> {
+    if music_info.track_id.trim().is_empty() {
+        return Err("Audio track id cannot be empty".to_string());
+    }
+
+    // Known tracks take their metadata from the registry
+    if state_handler::get_audio_track(&music_info.track_id).is_some() {
+        return Ok(());
+    }
+
+    if music_info.track_name.trim().is_empty() || music_info.artist_name.trim().is_empty() {
+        return Err("New audio tracks need a title and artist".to_string());
+    }
+
+    if music_info.track_name.len() > MAX_TRACK_TITLE_LENGTH || music_info.artist_name.len() > MAX_TRACK_TITLE_LENGTH {
+        return Err(format!(
+            "Track title and artist must be {} characters or less",
+            MAX_TRACK_TITLE_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
+// Counts a new use of the track, registering it on first use. The post or
+// story's copy of the metadata is replaced with the registry's.
+pub fn record_audio_use(music_info: &mut MusicInfo, post_id: Option<&str>) -> Result<(), String> {
+    validate_music_info(music_info)?;
+
+    let mut track = match state_handler::get_audio_track(&music_info.track_id) {
+        Some(track) => track,
+        None => AudioTrack {
+            track_id: music_info.track_id.clone(),
+            title: music_info.track_name.trim().to_string(),
+            artist: music_info.artist_name.trim().to_string(),
+            duration: music_info.duration,
+            original_post_id: post_id.map(|id| id.to_string()),
+            usage_count: 0,
+            created_at: state_handler::get_current_timestamp(),
+        },
+    };
+
+    track.usage_count += 1;
+    music_info.track_name = track.title.clone();
+    music_info.artist_name = track.artist.clone();
+
+    if let Some(post_id) = post_id {
+        state_handler::add_audio_post(&track.track_id, post_id);
+    }
+
+    state_handler::insert_audio_track(track);
+    Ok(())
+}
+
+pub fn get_audio_track(track_id: String) -> Result<AudioTrack, String> {
+    state_handler::get_audio_track(&track_id).ok_or_else(|| "Audio track not found".to_string())
+}
+
+pub fn get_audio_page(track_id: String, limit: u32, offset: u32) -> Result<AudioPage, String> {
+    let current_user = user_management::authenticate_user().ok();
+
+    let track = state_handler::get_audio_track(&track_id)
+        .ok_or_else(|| "Audio track not found".to_string())?;
+
+    let mut reels: Vec<Post> = state_handler::get_audio_posts(&track_id)
+        .into_iter()
+        .filter(|post| matches!(post.content_type, ContentType::Reel) && !post.is_archived)
+        .filter(|post| {
+            matches!(post.visibility, PostVisibility::Public)
+                || current_user.as_deref().is_some_and(|user_id| post.is_author(user_id))
+        })
+        .filter(|post| {
+            current_user
+                .as_deref()
+                .is_none_or(|user_id| !user_management::is_user_blocked(&post.user_id, user_id))
+        })
+        .collect();
+
+    // Most liked first
+    reels.sort_by_key(|post| std::cmp::Reverse(post.likes_count));
+
+    let reels = reels
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect();
+
+    Ok(AudioPage { track, reels })
+}
+
+pub fn search_audio(query: &str, limit: usize) -> Vec<AudioTrack> {
+    let query = query.trim().to_lowercase();
+
+    let mut tracks: Vec<AudioTrack> = state_handler::get_all_audio_tracks()
+        .into_iter()
+        .filter(|track| {
+            track.title.to_lowercase().contains(&query) || track.artist.to_lowercase().contains(&query)
+        })
+        .collect();
+
+    tracks.sort_by_key(|track| std::cmp::Reverse(track.usage_count));
+    tracks.truncate(limit);
+    tracks
+}
+
+// Ranks tracks by how many posts used them recently
+pub fn get_trending_audio(limit: u32) -> Result<Vec<AudioTrack>, String> {
+    let cutoff = state_handler::get_current_timestamp().saturating_sub(TRENDING_WINDOW);
+
+    let mut scored_tracks: Vec<(usize, AudioTrack)> = state_handler::get_all_audio_tracks()
+        .into_iter()
+        .filter(|track| track.usage_count > 0)
+        .map(|track| {
+            let recent_uses = state_handler::get_audio_posts(&track.track_id)
+                .iter()
+                .filter(|post| post.created_at >= cutoff)
+                .count();
+            (recent_uses, track)
+        })
+        .collect();
+
+    scored_tracks.sort_by_key(|(recent_uses, track)| std::cmp::Reverse((*recent_uses, track.usage_count)));
+
+    Ok(scored_tracks
+        .into_iter()
+        .take(limit as usize)
+        .map(|(_, track)| track)
+        .collect())
+}
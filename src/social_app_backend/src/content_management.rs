@@ -1,35 +1,215 @@
 use crate::types::*;
+use crate::audio;
+use crate::discovery;
+use crate::media_storage;
+use crate::notifications;
+use crate::safety_privacy;
 use crate::state_handler;
 use crate::user_management;
+use std::collections::HashSet;
+use std::time::Duration;
 
 pub fn create_post(post_data: CreatePostRequest) -> Result<Post, String> {
     let user_id = user_management::authenticate_user()?;
+    create_post_for_user(&user_id, post_data)
+}
+
+const MAX_CAROUSEL_ITEMS: usize = 10;
+const MAX_ALT_TEXT_LENGTH: usize = 100;
+const MAX_USER_TAGS_PER_ITEM: usize = 20;
+const MAX_PRODUCT_TAGS_PER_ITEM: usize = 5;
+const MAX_POST_POLL_OPTION_LENGTH: usize = 80;
+const MAX_POST_POLL_DURATION: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days in nanoseconds
+const POLL_CLOSE_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn validate_post_request(post_data: &CreatePostRequest, user_id: &str) -> Result<(), String> {
+    if post_data.caption.len() > 2200 {
+        return Err("Caption must be 2200 characters or less".to_string());
+    }
     
-    // Get user profile to get username
-    let user_profile = state_handler::get_user(&user_id)
-        .ok_or_else(|| "User profile not found".to_string())?;
+    if let Some(music_info) = &post_data.music_info {
+        audio::validate_music_info(music_info)?;
+    }
     
-    // Validate post data
-    if post_data.media_urls.is_empty() && post_data.caption.is_empty() {
-        return Err("Post must have either media or caption".to_string());
+    if let Some(poll) = &post_data.poll {
+        validate_post_poll(&post_data.content_type, poll)?;
     }
     
-    if post_data.caption.len() > 2200 {
-        return Err("Caption must be 2200 characters or less".to_string());
+    validate_post_media(&post_data.content_type, &post_data.media, user_id)
+}
+
+fn validate_post_poll(content_type: &ContentType, poll: &CreatePollRequest) -> Result<(), String> {
+    if matches!(content_type, ContentType::Story | ContentType::Live) {
+        return Err("Polls can only be added to feed posts".to_string());
+    }
+    
+    if poll.options.len() < MIN_POLL_OPTIONS || poll.options.len() > MAX_POLL_OPTIONS {
+        return Err(format!(
+            "A poll needs between {} and {} options",
+            MIN_POLL_OPTIONS, MAX_POLL_OPTIONS
+        ));
+    }
+    
+    for (index, option) in poll.options.iter().enumerate() {
+        if option.trim().is_empty() {
+            return Err("Poll options cannot be empty".to_string());
+        }
+        
+        if option.chars().count() > MAX_POST_POLL_OPTION_LENGTH {
+            return Err(format!(
+                "Poll options must be {} characters or less",
+                MAX_POST_POLL_OPTION_LENGTH
+            ));
+        }
+        
+        if poll.options[..index].iter().any(|other| other.trim().eq_ignore_ascii_case(option.trim())) {
+            return Err("Poll options must be unique".to_string());
+        }
+    }
+    
+    Ok(())
+}
+
+// The poll runs from when the post goes live, which is later than now for
+// scheduled posts
+pub fn validate_poll_window(poll: &CreatePollRequest, starts_at: u64) -> Result<(), String> {
+    if poll.ends_at <= starts_at {
+        return Err("Poll must end after the post is published".to_string());
+    }
+    
+    if poll.ends_at - starts_at > MAX_POST_POLL_DURATION {
+        return Err("Polls can run for at most 7 days".to_string());
+    }
+    
+    Ok(())
+}
+
+fn validate_post_media(content_type: &ContentType, media: &[MediaItem], user_id: &str) -> Result<(), String> {
+    let all_of_type = |media_type: MediaType| media.iter().all(|item| item.media_type == media_type);
+    
+    match content_type {
+        ContentType::Photo => {
+            if media.len() != 1 || !all_of_type(MediaType::Image) {
+                return Err("A photo post must have exactly one image".to_string());
+            }
+        }
+        ContentType::Video | ContentType::Reel | ContentType::Live => {
+            if media.len() != 1 || !all_of_type(MediaType::Video) {
+                return Err("This post type must have exactly one video".to_string());
+            }
+        }
+        ContentType::Carousel => {
+            if media.len() < 2 || media.len() > MAX_CAROUSEL_ITEMS {
+                return Err(format!("A carousel must have between 2 and {} items", MAX_CAROUSEL_ITEMS));
+            }
+        }
+        ContentType::Story => {
+            return Err("Stories must be created with create_story".to_string());
+        }
+    }
+    
+    for item in media {
+        validate_media_item(item, user_id)?;
+    }
+    
+    Ok(())
+}
+
+fn validate_media_item(item: &MediaItem, user_id: &str) -> Result<(), String> {
+    if item.url.trim().is_empty() {
+        return Err("Media URL cannot be empty".to_string());
     }
     
+    media_storage::validate_media_url(&item.url, user_id)?;
+    
+    if item.width == Some(0) || item.height == Some(0) {
+        return Err("Media dimensions must be greater than zero".to_string());
+    }
+    
+    if let Some(alt_text) = &item.alt_text {
+        if alt_text.chars().count() > MAX_ALT_TEXT_LENGTH {
+            return Err(format!("Alt text must be {} characters or less", MAX_ALT_TEXT_LENGTH));
+        }
+    }
+    
+    if item.user_tags.len() > MAX_USER_TAGS_PER_ITEM {
+        return Err(format!("Maximum of {} people can be tagged per item", MAX_USER_TAGS_PER_ITEM));
+    }
+    
+    for (index, tag) in item.user_tags.iter().enumerate() {
+        if item.user_tags[..index].iter().any(|other| other.user_id == tag.user_id) {
+            return Err("A user can only be tagged once per item".to_string());
+        }
+    }
+    
+    if item.product_tags.len() > MAX_PRODUCT_TAGS_PER_ITEM {
+        return Err(format!("Maximum of {} products can be tagged per item", MAX_PRODUCT_TAGS_PER_ITEM));
+    }
+    
+    Ok(())
+}
+
+// Shared by direct and scheduled publishing
+pub fn create_post_for_user(user_id: &str, post_data: CreatePostRequest) -> Result<Post, String> {
+    // Get user profile to get username
+    let user_profile = state_handler::get_user(user_id)
+        .ok_or_else(|| "User profile not found".to_string())?;
+    
+    validate_post_request(&post_data, user_id)?;
+    
     let post_id = state_handler::generate_id();
     let current_time = state_handler::get_current_timestamp();
     
-    let post = Post {
+    let collaborators = build_collaborator_invites(
+        user_id,
+        post_data.collaborators.as_deref().unwrap_or_default(),
+        current_time,
+    )?;
+    
+    let remix_of = match &post_data.remix_of {
+        Some(source_post_id) => Some(build_remix_attribution(
+            user_id,
+            &post_data.content_type,
+            source_post_id,
+            post_data.remix_type.clone().unwrap_or(RemixType::Remix),
+        )?),
+        None => None,
+    };
+    
+    let (tagged_users, pending_tagged_users) = resolve_user_tags(user_id, &post_data)?;
+    
+    let poll = match post_data.poll {
+        Some(poll) => {
+            validate_poll_window(&poll, current_time)?;
+            Some(PostPoll {
+                options: poll
+                    .options
+                    .into_iter()
+                    .map(|text| PollOption {
+                        text: text.trim().to_string(),
+                        votes_count: 0,
+                        percentage: 0.0,
+                    })
+                    .collect(),
+                total_votes: 0,
+                ends_at: poll.ends_at,
+                is_closed: false,
+                viewer_vote: None,
+            })
+        }
+        None => None,
+    };
+    
+    let mut post = Post {
         post_id: post_id.clone(),
-        user_id: user_id.clone(),
+        user_id: user_id.to_string(),
         username: user_profile.username.clone(),
         content_type: post_data.content_type,
-        media_urls: post_data.media_urls,
+        media: post_data.media,
         caption: post_data.caption,
         hashtags: post_data.hashtags.clone(),
-        tagged_users: post_data.tagged_users,
+        tagged_users,
+        pending_tagged_users,
         location: post_data.location,
         likes_count: 0,
         comments_count: 0,
@@ -37,24 +217,99 @@ pub fn create_post(post_data: CreatePostRequest) -> Result<Post, String> {
         created_at: current_time,
         updated_at: current_time,
         is_archived: false,
+        is_pinned: false,
+        is_edited: false,
         visibility: post_data.visibility,
         music_info: post_data.music_info,
         product_tags: post_data.product_tags,
+        collaborators,
+        remix_of,
+        allow_remixes: post_data.allow_remixes.unwrap_or(true),
+        remixes_count: 0,
+        hide_like_count: post_data.hide_like_count.unwrap_or(false),
+        content_warning: post_data.content_warning,
+        is_blurred: false,
+        poll,
         post_type: (),
     };
     
+    if let Some(music_info) = &mut post.music_info {
+        audio::record_audio_use(music_info, Some(&post_id))?;
+    }
+    
     // Insert hashtags
     for hashtag in post_data.hashtags {
         state_handler::insert_hashtag(hashtag, post_id.clone());
     }
     
-    state_handler::insert_post(post_id, post.clone());
+    state_handler::retain_media_items(&post.media);
+    state_handler::insert_post(post_id.clone(), post.clone());
+    
+    if let Some(poll) = &post.poll {
+        state_handler::add_open_poll(poll.ends_at, post_id.clone());
+    }
+    
+    if let Some(remix_of) = &post.remix_of {
+        state_handler::add_post_remix(&remix_of.source_post_id, &post_id);
+        let action = match remix_of.remix_type {
+            RemixType::Remix => "remixed",
+            RemixType::Duet => "made a duet with",
+        };
+        let _ = notifications::send_notification(
+            remix_of.source_user_id.clone(),
+            NotificationType::Remix,
+            "Your Reel Was Remixed".to_string(),
+            format!("{} {} your reel", user_profile.username, action),
+            Some(user_id.to_string()),
+            Some(post_id.clone()),
+            None,
+        );
+    }
+    
+    for tagged_user_id in post.tagged_users.iter().filter(|id| id.as_str() != user_id) {
+        state_handler::add_tagged_post(tagged_user_id, &post_id);
+        let _ = notifications::send_notification(
+            tagged_user_id.clone(),
+            NotificationType::Tag,
+            "New Tag".to_string(),
+            format!("{} tagged you in a post", user_profile.username),
+            Some(user_id.to_string()),
+            Some(post_id.clone()),
+            None,
+        );
+    }
+    
+    for tagged_user_id in &post.pending_tagged_users {
+        state_handler::add_tagged_post(tagged_user_id, &post_id);
+        let _ = notifications::send_notification(
+            tagged_user_id.clone(),
+            NotificationType::Request,
+            "Tag Request".to_string(),
+            format!("{} wants to tag you in a post", user_profile.username),
+            Some(user_id.to_string()),
+            Some(post_id.clone()),
+            None,
+        );
+    }
+    
+    for collaborator in &post.collaborators {
+        state_handler::add_collab_invite(&collaborator.user_id, &post_id);
+        let _ = notifications::send_notification(
+            collaborator.user_id.clone(),
+            NotificationType::Request,
+            "Collaboration Invite".to_string(),
+            format!("{} invited you to collaborate on a post", user_profile.username),
+            Some(user_id.to_string()),
+            Some(post_id.clone()),
+            None,
+        );
+    }
     
     // Update user's post count
     let mut updated_user = user_profile;
     updated_user.posts_count += 1;
     updated_user.updated_at = current_time;
-    state_handler::update_user(&user_id, updated_user)?;
+    state_handler::update_user(user_id, updated_user)?;
     
     Ok(post)
 }
@@ -84,27 +339,67 @@ pub fn update_post(post_id: String, caption: Option<String>, hashtags: Option<Ve
         return Err("Access denied".to_string());
     }
     
-    if let Some(new_caption) = caption {
+    if let Some(new_caption) = &caption {
         if new_caption.len() > 2200 {
             return Err("Caption must be 2200 characters or less".to_string());
         }
+    }
+    
+    let caption_changed = caption.as_ref().is_some_and(|new_caption| *new_caption != post.caption);
+    let hashtags_changed = hashtags.as_ref().is_some_and(|new_hashtags| *new_hashtags != post.hashtags);
+    
+    if !caption_changed && !hashtags_changed {
+        return Ok(post);
+    }
+    
+    let current_time = state_handler::get_current_timestamp();
+    
+    // Keep the previous version before overwriting it
+    let revision = PostRevision {
+        post_id: post_id.clone(),
+        revision_number: state_handler::get_post_revisions(&post_id).len() as u32 + 1,
+        caption: post.caption.clone(),
+        hashtags: post.hashtags.clone(),
+        edited_at: current_time,
+    };
+    state_handler::add_post_revision(post_id.clone(), revision);
+    
+    if let Some(new_caption) = caption {
         post.caption = new_caption;
     }
     
     if let Some(new_hashtags) = hashtags {
-        post.hashtags = new_hashtags.clone();
-        // Update hashtag index
-        for hashtag in new_hashtags {
-            state_handler::insert_hashtag(hashtag, post_id.clone());
+        // Update hashtag index so removed hashtags stop pointing at the post
+        for hashtag in post.hashtags.iter().filter(|h| !new_hashtags.contains(h)) {
+            state_handler::remove_hashtag(hashtag, &post_id);
         }
+        for hashtag in new_hashtags.iter().filter(|h| !post.hashtags.contains(h)) {
+            state_handler::insert_hashtag(hashtag.clone(), post_id.clone());
+        }
+        post.hashtags = new_hashtags;
     }
     
-    post.updated_at = state_handler::get_current_timestamp();
+    post.is_edited = true;
+    post.updated_at = current_time;
     
     state_handler::insert_post(post_id, post.clone());
     Ok(post)
 }
 
+pub fn get_post_revisions(post_id: String) -> Result<Vec<PostRevision>, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+    
+    // Edit history is only available to the owner and moderators
+    if post.user_id != user_id && !user_management::is_moderator(&user_id) {
+        return Err("Access denied".to_string());
+    }
+    
+    Ok(state_handler::get_post_revisions(&post_id))
+}
+
 pub fn delete_post(post_id: String) -> Result<(), String> {
     let user_id = user_management::authenticate_user()?;
     
@@ -116,6 +411,8 @@ pub fn delete_post(post_id: String) -> Result<(), String> {
         return Err("Access denied".to_string());
     }
     
+    state_handler::unpin_post(&user_id, &post_id);
+    release_collaborators(&post);
     state_handler::delete_post(&post_id)?;
     
     // Update user's post count
@@ -128,203 +425,1704 @@ pub fn delete_post(post_id: String) -> Result<(), String> {
     Ok(())
 }
 
-pub fn get_feed(limit: u32, offset: u32) -> Result<Vec<Post>, String> {
-    let user_id = user_management::authenticate_user()?;
-    
-    // Get users that current user follows
-    let following = state_handler::get_following(&user_id);
-    
-    // Get all posts and filter by following users
-    let all_posts = state_handler::get_all_posts();
-    let mut feed_posts: Vec<Post> = all_posts
-        .into_iter()
-        .filter(|post| {
-            following.contains(&post.user_id) || post.user_id == user_id
-        })
-        .filter(|post| can_view_post(post, Some(&user_id)))
-        .collect();
-    
-    // Sort by creation time (newest first)
-    feed_posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-    
-    // Apply pagination
-    let start = offset as usize;
-    let end = start + limit as usize;
-    
-    if start >= feed_posts.len() {
-        return Ok(Vec::new());
-    }
-    
-    let end = end.min(feed_posts.len());
-    Ok(feed_posts[start..end].to_vec())
-}
-
-pub fn get_user_posts(user_id: &str, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
-    let current_user = user_management::authenticate_user().ok();
-    
-    let user_posts = state_handler::get_user_posts(user_id);
-    
-    // Filter posts based on visibility and user permissions
-    let visible_posts: Vec<Post> = user_posts
-        .into_iter()
-        .filter(|post| can_view_post(post, current_user.as_deref()))
-        .collect();
-    
-    // Sort by creation time (newest first)
-    let mut sorted_posts = visible_posts;
-    sorted_posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-    
-    // Apply pagination
-    let start = offset as usize;
-    let end = start + limit as usize;
-    
-    if start >= sorted_posts.len() {
-        return Ok(Vec::new());
-    }
-    
-    let end = end.min(sorted_posts.len());
-    Ok(sorted_posts[start..end].to_vec())
-}
-
-pub fn archive_post(post_id: String) -> Result<Post, String> {
+pub fn set_hide_like_count(post_id: String, hidden: bool) -> Result<Post, String> {
     let user_id = user_management::authenticate_user()?;
     
     let mut post = state_handler::get_post(&post_id)
         .ok_or_else(|| "Post not found".to_string())?;
     
-    // Check if user owns the post
     if post.user_id != user_id {
         return Err("Access denied".to_string());
     }
     
-    post.is_archived = true;
+    post.hide_like_count = hidden;
     post.updated_at = state_handler::get_current_timestamp();
     
     state_handler::insert_post(post_id, post.clone());
     Ok(post)
 }
 
-pub fn unarchive_post(post_id: String) -> Result<Post, String> {
+// Authors and moderators can label a post; pass None to clear the label
+pub fn set_post_content_warning(post_id: String, warning: Option<ContentWarning>) -> Result<Post, String> {
     let user_id = user_management::authenticate_user()?;
     
     let mut post = state_handler::get_post(&post_id)
         .ok_or_else(|| "Post not found".to_string())?;
     
-    // Check if user owns the post
-    if post.user_id != user_id {
+    if post.user_id != user_id && !user_management::is_moderator(&user_id) {
         return Err("Access denied".to_string());
     }
     
-    post.is_archived = false;
+    post.content_warning = warning;
     post.updated_at = state_handler::get_current_timestamp();
     
     state_handler::insert_post(post_id, post.clone());
     Ok(post)
 }
 
-pub fn get_archived_posts(limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+pub fn set_story_content_warning(story_id: String, warning: Option<ContentWarning>) -> Result<Story, String> {
     let user_id = user_management::authenticate_user()?;
     
-    let user_posts = state_handler::get_user_posts(&user_id);
-    
-    // Filter for archived posts only
-    let archived_posts: Vec<Post> = user_posts
-        .into_iter()
-        .filter(|post| post.is_archived)
-        .collect();
-    
-    // Sort by creation time (newest first)
-    let mut sorted_posts = archived_posts;
-    sorted_posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-    
-    // Apply pagination
-    let start = offset as usize;
-    let end = start + limit as usize;
+    let mut story = state_handler::get_story(&story_id)
+        .ok_or_else(|| "Story not found".to_string())?;
     
-    if start >= sorted_posts.len() {
-        return Ok(Vec::new());
+    if story.user_id != user_id && !user_management::is_moderator(&user_id) {
+        return Err("Access denied".to_string());
     }
     
-    let end = end.min(sorted_posts.len());
-    Ok(sorted_posts[start..end].to_vec())
-}
-
-pub fn create_story(media_url: String, story_type: StoryType, text_overlay: Option<String>, stickers: Vec<Sticker>, music_info: Option<MusicInfo>) -> Result<Story, String> {
-    let user_id = user_management::authenticate_user()?;
-    
-    // Get user profile to get username
-    let user_profile = state_handler::get_user(&user_id)
-        .ok_or_else(|| "User profile not found".to_string())?;
-    
-    let story_id = state_handler::generate_id();
-    let current_time = state_handler::get_current_timestamp();
-    let expires_at = current_time + (24 * 60 * 60 * 1000); // 24 hours in milliseconds
-    
-    let story = Story {
-        story_id: story_id.clone(),
-        user_id,
-        username: user_profile.username,
-        media_url,
-        story_type,
-        text_overlay,
-        stickers,
-        music_info,
-        viewers: Vec::new(),
-        created_at: current_time,
-        expires_at,
-        is_highlight: false,
-        highlight_id: None,
-    };
+    story.content_warning = warning;
     
     state_handler::insert_story(story_id, story.clone());
     Ok(story)
 }
 
-pub fn view_story(story_id: String) -> Result<Story, String> {
+// One vote per user; voting again moves the vote to the new option
+pub fn vote_on_poll(post_id: String, option_index: u32) -> Result<Post, String> {
     let user_id = user_management::authenticate_user()?;
     
-    let mut story = state_handler::get_story(&story_id)
-        .ok_or_else(|| "Story not found".to_string())?;
+    let mut post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
     
-    // Check if story has expired
-    if story.expires_at < state_handler::get_current_timestamp() && !story.is_highlight {
-        return Err("Story has expired".to_string());
+    if !can_view_post(&post, Some(&user_id)) {
+        return Err("Access denied".to_string());
     }
     
-    // Add viewer if not already in the list
-    if !story.viewers.contains(&user_id) {
-        story.viewers.push(user_id);
-        state_handler::insert_story(story_id, story.clone());
+    let poll = post.poll.as_mut().ok_or_else(|| "Post has no poll".to_string())?;
+    
+    if poll.is_closed || poll.ends_at <= state_handler::get_current_timestamp() {
+        return Err("Poll has ended".to_string());
     }
     
-    Ok(story)
-}
-
-pub fn get_user_stories(user_id: &str) -> Result<Vec<Story>, String> {
-    let current_user = user_management::authenticate_user().ok();
+    if option_index as usize >= poll.options.len() {
+        return Err("Invalid poll option".to_string());
+    }
     
-    // Check if user can view stories (not blocked, etc.)
-    if let Some(current_user_id) = &current_user {
-        if user_management::is_user_blocked(user_id, current_user_id) {
-            return Err("Access denied".to_string());
+    match state_handler::set_poll_vote(post_id.clone(), user_id, option_index) {
+        Some(previous) if previous == option_index => return Ok(post),
+        Some(previous) => {
+            let previous_option = &mut poll.options[previous as usize];
+            previous_option.votes_count = previous_option.votes_count.saturating_sub(1);
         }
+        None => poll.total_votes += 1,
     }
+    poll.options[option_index as usize].votes_count += 1;
     
-    let stories = state_handler::get_user_stories(user_id);
-    let current_time = state_handler::get_current_timestamp();
-    
-    // Filter out expired stories
-    let active_stories: Vec<Story> = stories
-        .into_iter()
-        .filter(|story| story.expires_at > current_time || story.is_highlight)
-        .collect();
-    
-    Ok(active_stories)
+    state_handler::insert_post(post_id, post.clone());
+    Ok(post)
 }
 
-fn can_view_post(post: &Post, current_user_id: Option<&str>) -> bool {
-    match post.visibility {
-        PostVisibility::Public => true,
+// Timers don't survive upgrades, so this runs from both init and post_upgrade
+pub fn start_poll_close_timer() {
+    ic_cdk_timers::set_timer_interval(POLL_CLOSE_INTERVAL, close_due_polls);
+}
+
+// Votes are already refused past ends_at; closing marks the results final
+// and tells the author how the poll went
+fn close_due_polls() {
+    let current_time = state_handler::get_current_timestamp();
+    
+    for post_id in state_handler::take_due_poll_post_ids(current_time) {
+        let Some(mut post) = state_handler::get_post(&post_id) else {
+            continue;
+        };
+        let Some(poll) = post.poll.as_mut() else {
+            continue;
+        };
+        
+        poll.is_closed = true;
+        let _ = notifications::notify_poll_ended(post.user_id.clone(), post_id.clone(), poll);
+        
+        state_handler::insert_post(post_id, post);
+    }
+}
+
+fn prepare_poll_for_viewer(post_id: &str, poll: &mut PostPoll, viewer_id: Option<&str>) {
+    poll.is_closed = poll.is_closed || poll.ends_at <= state_handler::get_current_timestamp();
+    poll.viewer_vote = viewer_id.and_then(|user_id| state_handler::get_poll_vote(post_id, user_id));
+    
+    for option in &mut poll.options {
+        option.percentage = if poll.total_votes == 0 {
+            0.0
+        } else {
+            option.votes_count as f32 * 100.0 / poll.total_votes as f32
+        };
+    }
+}
+
+// Like counts are masked for everyone but the authors when the post or the
+// author's privacy settings ask for it, and labeled posts come back blurred
+pub fn prepare_post_for_viewer(mut post: Post, viewer_id: Option<&str>) -> Post {
+    if let Some(poll) = &mut post.poll {
+        prepare_poll_for_viewer(&post.post_id, poll, viewer_id);
+    }
+    
+    if viewer_id.is_some_and(|user_id| post.is_author(user_id)) {
+        return post;
+    }
+    
+    post.is_blurred = post.content_warning.is_some();
+    
+    // Pending tags only show up once the tagged user approves them, except to
+    // the tagged user so they can review their own
+    if !post.pending_tagged_users.is_empty() {
+        let mut pending = std::mem::take(&mut post.pending_tagged_users);
+        if let Some(user_id) = viewer_id.filter(|user_id| pending.iter().any(|pending_id| pending_id == user_id)) {
+            pending.retain(|pending_id| pending_id != user_id);
+            post.pending_tagged_users.push(user_id.to_string());
+        }
+        for item in &mut post.media {
+            item.user_tags.retain(|tag| !pending.contains(&tag.user_id));
+        }
+    }
+    
+    let author_hides_likes = state_handler::get_privacy_settings(&post.user_id)
+        .is_some_and(|settings| settings.hide_likes);
+    
+    if post.hide_like_count || author_hides_likes {
+        post.likes_count = 0;
+        post.hide_like_count = true;
+    }
+    
+    post
+}
+
+// Collects everyone tagged in the caption list or on media and splits them
+// into approved and pending tags according to each user's tag settings
+fn resolve_user_tags(user_id: &str, post_data: &CreatePostRequest) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut tagged_user_ids: Vec<String> = Vec::new();
+    let media_tags = post_data.media.iter().flat_map(|item| item.user_tags.iter().map(|tag| &tag.user_id));
+    for tagged_user_id in post_data.tagged_users.iter().chain(media_tags) {
+        if !tagged_user_ids.contains(tagged_user_id) {
+            tagged_user_ids.push(tagged_user_id.clone());
+        }
+    }
+    
+    let mut tagged_users = Vec::new();
+    let mut pending_tagged_users = Vec::new();
+    
+    for tagged_user_id in tagged_user_ids {
+        if !state_handler::user_exists(&tagged_user_id) {
+            return Err(format!("Tagged user {} not found", tagged_user_id));
+        }
+        
+        if safety_privacy::check_tag_allowed(user_id, &tagged_user_id)? {
+            pending_tagged_users.push(tagged_user_id);
+        } else {
+            tagged_users.push(tagged_user_id);
+        }
+    }
+    
+    Ok((tagged_users, pending_tagged_users))
+}
+
+pub fn approve_tag(post_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+    
+    if !post.pending_tagged_users.contains(&user_id) {
+        return Err("No pending tag on this post".to_string());
+    }
+    
+    post.pending_tagged_users.retain(|id| id != &user_id);
+    post.tagged_users.push(user_id);
+    
+    state_handler::insert_post(post_id, post.clone());
+    Ok(post)
+}
+
+// Declines a pending tag or removes an approved one
+pub fn remove_tag(post_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    state_handler::get_post(&post_id).ok_or_else(|| "Post not found".to_string())?;
+    
+    if !state_handler::remove_user_tag(&post_id, &user_id) {
+        return Err("You are not tagged in this post".to_string());
+    }
+    
+    Ok(())
+}
+
+pub fn get_pending_tags() -> Result<Vec<Post>, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut pending_posts: Vec<Post> = state_handler::get_tagged_posts(&user_id)
+        .into_iter()
+        .filter(|post| post.pending_tagged_users.contains(&user_id))
+        .collect();
+    
+    pending_posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+    Ok(pending_posts)
+}
+
+// Backs the "tagged" tab on a profile
+pub fn get_tagged_posts(user_id: &str, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+    let current_user = user_management::authenticate_user().ok();
+    
+    if !state_handler::user_exists(user_id) {
+        return Err("User not found".to_string());
+    }
+    
+    if !can_view_profile_content(user_id, current_user.as_deref()) {
+        return Err("Access denied".to_string());
+    }
+    
+    let mut tagged_posts: Vec<Post> = state_handler::get_tagged_posts(user_id)
+        .into_iter()
+        .filter(|post| post.tagged_users.iter().any(|id| id == user_id))
+        .filter(|post| !post.is_archived && can_view_post(post, current_user.as_deref()))
+        .collect();
+    
+    tagged_posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+    
+    Ok(tagged_posts
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect())
+}
+
+pub fn prepare_post_for_caller(post: Post) -> Post {
+    let current_user = user_management::authenticate_user().ok();
+    prepare_post_for_viewer(post, current_user.as_deref())
+}
+
+pub fn prepare_posts_for_caller(posts: Vec<Post>) -> Vec<Post> {
+    let current_user = user_management::authenticate_user().ok();
+    posts
+        .into_iter()
+        .map(|post| prepare_post_for_viewer(post, current_user.as_deref()))
+        .collect()
+}
+
+pub fn set_remixes_allowed(post_id: String, allowed: bool) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+    
+    if post.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+    
+    post.allow_remixes = allowed;
+    post.updated_at = state_handler::get_current_timestamp();
+    
+    state_handler::insert_post(post_id, post.clone());
+    Ok(post)
+}
+
+pub fn get_post_remixes(post_id: String, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+    let source = get_post(&post_id)?;
+    let current_user = user_management::authenticate_user().ok();
+    
+    let mut remixes: Vec<Post> = state_handler::get_post_remixes(&source.post_id)
+        .into_iter()
+        .filter(|post| !post.is_archived && can_view_post(post, current_user.as_deref()))
+        .collect();
+    
+    // Sort by creation time (newest first)
+    remixes.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+    
+    Ok(remixes
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect())
+}
+
+fn build_remix_attribution(user_id: &str, content_type: &ContentType, source_post_id: &str, remix_type: RemixType) -> Result<RemixAttribution, String> {
+    if !matches!(content_type, ContentType::Reel) {
+        return Err("Only reels can be remixes".to_string());
+    }
+    
+    let source = state_handler::get_post(source_post_id)
+        .ok_or_else(|| "Source reel not found".to_string())?;
+    
+    if !matches!(source.content_type, ContentType::Reel) {
+        return Err("Only reels can be remixed".to_string());
+    }
+    
+    // Remixes are only allowed from public reels whose owner opted in
+    if !source.allow_remixes || source.is_archived || !matches!(source.visibility, PostVisibility::Public) {
+        return Err("This reel can't be remixed".to_string());
+    }
+    
+    let source_is_private = state_handler::get_user(&source.user_id)
+        .is_none_or(|source_author| source_author.is_private);
+    
+    if source_is_private
+        || user_management::is_user_blocked(&source.user_id, user_id)
+        || user_management::is_user_blocked(user_id, &source.user_id)
+    {
+        return Err("This reel can't be remixed".to_string());
+    }
+    
+    Ok(RemixAttribution {
+        source_post_id: source.post_id,
+        source_user_id: source.user_id,
+        source_username: source.username,
+        remix_type,
+    })
+}
+
+const MAX_COLLABORATORS: usize = 3;
+
+pub fn get_collab_invitations() -> Result<Vec<Post>, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let posts = state_handler::get_collab_invites(&user_id)
+        .iter()
+        .filter_map(|post_id| state_handler::get_post(post_id))
+        .collect();
+    
+    Ok(posts)
+}
+
+pub fn accept_collab_invitation(post_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut post = get_pending_collab_post(&post_id, &user_id)?;
+    let current_time = state_handler::get_current_timestamp();
+    
+    if let Some(collaborator) = post.collaborators.iter_mut().find(|c| c.user_id == user_id) {
+        collaborator.status = CollaboratorStatus::Accepted;
+        collaborator.responded_at = Some(current_time);
+    }
+    
+    state_handler::insert_post(post_id.clone(), post.clone());
+    state_handler::remove_collab_invite(&user_id, &post_id);
+    
+    // The post now shows on the co-author's profile as well
+    state_handler::add_user_post(&user_id, &post_id);
+    adjust_posts_count(&user_id, 1)?;
+    
+    let username = state_handler::get_user(&user_id)
+        .map(|profile| profile.username)
+        .unwrap_or_default();
+    
+    let _ = notifications::send_notification(
+        post.user_id.clone(),
+        NotificationType::Request,
+        "Collaboration Accepted".to_string(),
+        format!("{} accepted your collaboration invite", username),
+        Some(user_id.clone()),
+        Some(post_id.clone()),
+        None,
+    );
+    let _ = notifications::send_notification(
+        user_id,
+        NotificationType::Request,
+        "Collaboration Accepted".to_string(),
+        format!("You are now a co-author of {}'s post", post.username),
+        Some(post.user_id.clone()),
+        Some(post_id),
+        None,
+    );
+    
+    Ok(post)
+}
+
+pub fn decline_collab_invitation(post_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut post = get_pending_collab_post(&post_id, &user_id)?;
+    
+    post.collaborators.retain(|c| c.user_id != user_id);
+    state_handler::insert_post(post_id.clone(), post);
+    state_handler::remove_collab_invite(&user_id, &post_id);
+    
+    Ok(())
+}
+
+// Either author can step away from a collab post. When the owner leaves, the
+// first co-author takes the post over so it stays up.
+pub fn remove_self_from_collab(post_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+    
+    if !post.is_author(&user_id) {
+        return Err("You are not an author of this post".to_string());
+    }
+    
+    let mut notify_user_ids = Vec::new();
+    
+    if post.user_id == user_id {
+        let new_owner_index = post.collaborators
+            .iter()
+            .position(|c| c.status == CollaboratorStatus::Accepted)
+            .ok_or_else(|| "This post has no co-author to hand it over to; delete it instead".to_string())?;
+        let new_owner = post.collaborators.remove(new_owner_index);
+        
+        post.user_id = new_owner.user_id;
+        post.username = new_owner.username;
+        post.is_pinned = false;
+        state_handler::unpin_post(&user_id, &post_id);
+        notify_user_ids.push(post.user_id.clone());
+    } else {
+        post.collaborators.retain(|c| c.user_id != user_id);
+        notify_user_ids.push(post.user_id.clone());
+    }
+    
+    state_handler::insert_post(post_id.clone(), post.clone());
+    state_handler::remove_user_post(&user_id, &post_id);
+    adjust_posts_count(&user_id, -1)?;
+    
+    let username = state_handler::get_user(&user_id)
+        .map(|profile| profile.username)
+        .unwrap_or_default();
+    
+    for notify_user_id in notify_user_ids {
+        let _ = notifications::send_notification(
+            notify_user_id,
+            NotificationType::System,
+            "Collaboration Ended".to_string(),
+            format!("{} removed themselves from your collab post", username),
+            Some(user_id.clone()),
+            Some(post_id.clone()),
+            None,
+        );
+    }
+    
+    Ok(post)
+}
+
+// Takes the post off co-authors' profiles before it is deleted
+pub fn release_collaborators(post: &Post) {
+    for collaborator in &post.collaborators {
+        if collaborator.status == CollaboratorStatus::Accepted {
+            let _ = adjust_posts_count(&collaborator.user_id, -1);
+        }
+    }
+}
+
+// Removes a departing user from every post they co-author
+pub fn leave_all_collab_posts(user_id: &str) {
+    for post_id in state_handler::get_collab_invites(user_id) {
+        if let Some(mut post) = state_handler::get_post(&post_id) {
+            post.collaborators.retain(|c| c.user_id != user_id);
+            state_handler::insert_post(post_id, post);
+        }
+    }
+    
+    for mut post in state_handler::get_posts_by_user(user_id) {
+        if post.user_id != user_id {
+            post.collaborators.retain(|c| c.user_id != user_id);
+            state_handler::remove_user_post(user_id, &post.post_id);
+            state_handler::insert_post(post.post_id.clone(), post);
+        }
+    }
+}
+
+fn build_collaborator_invites(user_id: &str, invitee_ids: &[String], current_time: u64) -> Result<Vec<PostCollaborator>, String> {
+    if invitee_ids.len() > MAX_COLLABORATORS {
+        return Err(format!("Maximum of {} collaborators allowed", MAX_COLLABORATORS));
+    }
+    
+    let mut collaborators: Vec<PostCollaborator> = Vec::new();
+    
+    for invitee_id in invitee_ids {
+        if invitee_id == user_id {
+            return Err("You cannot invite yourself to collaborate".to_string());
+        }
+        
+        if collaborators.iter().any(|c| &c.user_id == invitee_id) {
+            continue;
+        }
+        
+        let invitee = state_handler::get_user(invitee_id)
+            .ok_or_else(|| "Collaborator not found".to_string())?;
+        
+        if user_management::is_user_blocked(user_id, invitee_id) || user_management::is_user_blocked(invitee_id, user_id) {
+            return Err("Cannot invite this user to collaborate".to_string());
+        }
+        
+        collaborators.push(PostCollaborator {
+            user_id: invitee.user_id,
+            username: invitee.username,
+            status: CollaboratorStatus::Pending,
+            invited_at: current_time,
+            responded_at: None,
+        });
+    }
+    
+    Ok(collaborators)
+}
+
+fn get_pending_collab_post(post_id: &str, user_id: &str) -> Result<Post, String> {
+    let post = state_handler::get_post(post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+    
+    let is_pending = post.collaborators
+        .iter()
+        .any(|c| c.user_id == user_id && c.status == CollaboratorStatus::Pending);
+    
+    if !is_pending {
+        return Err("No pending collaboration invite for this post".to_string());
+    }
+    
+    Ok(post)
+}
+
+fn adjust_posts_count(user_id: &str, delta: i64) -> Result<(), String> {
+    if let Some(mut user_profile) = state_handler::get_user(user_id) {
+        user_profile.posts_count = user_profile.posts_count.saturating_add_signed(delta);
+        user_profile.updated_at = state_handler::get_current_timestamp();
+        state_handler::update_user(user_id, user_profile)?;
+    }
+    Ok(())
+}
+
+const MAX_DRAFTS: usize = 100;
+
+pub fn save_draft(post_data: CreatePostRequest) -> Result<PostDraft, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    validate_draft(&post_data, &user_id)?;
+    
+    if state_handler::get_user_drafts(&user_id).len() >= MAX_DRAFTS {
+        return Err(format!("Maximum of {} drafts allowed", MAX_DRAFTS));
+    }
+    
+    let current_time = state_handler::get_current_timestamp();
+    let draft = PostDraft {
+        draft_id: state_handler::generate_id(),
+        user_id: user_id.clone(),
+        post_data,
+        created_at: current_time,
+        updated_at: current_time,
+    };
+    
+    state_handler::upsert_draft(&user_id, draft.clone());
+    Ok(draft)
+}
+
+pub fn get_drafts() -> Result<Vec<PostDraft>, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut drafts = state_handler::get_user_drafts(&user_id);
+    
+    // Most recently edited first
+    drafts.sort_by_key(|draft| std::cmp::Reverse(draft.updated_at));
+    
+    Ok(drafts)
+}
+
+pub fn update_draft(draft_id: String, post_data: CreatePostRequest) -> Result<PostDraft, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut draft = state_handler::get_draft(&user_id, &draft_id)
+        .ok_or_else(|| "Draft not found".to_string())?;
+    
+    validate_draft(&post_data, &user_id)?;
+    
+    draft.post_data = post_data;
+    draft.updated_at = state_handler::get_current_timestamp();
+    
+    state_handler::upsert_draft(&user_id, draft.clone());
+    Ok(draft)
+}
+
+pub fn delete_draft(draft_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    if !state_handler::remove_draft(&user_id, &draft_id) {
+        return Err("Draft not found".to_string());
+    }
+    
+    Ok(())
+}
+
+pub fn publish_draft(draft_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let draft = state_handler::get_draft(&user_id, &draft_id)
+        .ok_or_else(|| "Draft not found".to_string())?;
+    
+    // The draft is only discarded once the post was actually created
+    let post = create_post(draft.post_data)?;
+    state_handler::remove_draft(&user_id, &draft_id);
+    
+    Ok(post)
+}
+
+// Drafts may be incomplete, so only limits are enforced here; the full
+// validation runs when the draft is published
+fn validate_draft(post_data: &CreatePostRequest, user_id: &str) -> Result<(), String> {
+    if post_data.caption.len() > 2200 {
+        return Err("Caption must be 2200 characters or less".to_string());
+    }
+    
+    if post_data.media.len() > MAX_CAROUSEL_ITEMS {
+        return Err(format!("A post can have at most {} items", MAX_CAROUSEL_ITEMS));
+    }
+    
+    for item in &post_data.media {
+        validate_media_item(item, user_id)?;
+    }
+    
+    Ok(())
+}
+
+pub fn get_feed(limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    // Get users that current user follows
+    let following = state_handler::get_following(&user_id);
+    
+    // Get all posts and filter by following users
+    let all_posts = state_handler::get_all_posts();
+    let mut feed_posts: Vec<Post> = all_posts
+        .into_iter()
+        .filter(|post| {
+            following.contains(&post.user_id) || post.user_id == user_id
+        })
+        .filter(|post| can_view_post(post, Some(&user_id)))
+        .collect();
+    
+    // Mix in the newest posts from followed hashtags; no page can reach past
+    // offset + limit posts of any one hashtag
+    let per_hashtag = offset as usize + limit as usize;
+    let mut seen_post_ids: HashSet<String> = feed_posts.iter().map(|post| post.post_id.clone()).collect();
+    for hashtag in state_handler::get_followed_hashtags(&user_id) {
+        for post in discovery::get_recent_hashtag_posts(&hashtag, Some(&user_id), per_hashtag) {
+            if seen_post_ids.insert(post.post_id.clone()) {
+                feed_posts.push(post);
+            }
+        }
+    }
+    
+    // Sort by creation time (newest first)
+    feed_posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    
+    // Apply pagination
+    let start = offset as usize;
+    let end = start + limit as usize;
+    
+    if start >= feed_posts.len() {
+        return Ok(Vec::new());
+    }
+    
+    let end = end.min(feed_posts.len());
+    Ok(feed_posts[start..end].to_vec())
+}
+
+pub fn get_user_posts(user_id: &str, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+    let current_user = user_management::authenticate_user().ok();
+    
+    let user_posts = state_handler::get_user_posts(user_id);
+    
+    // Filter posts based on visibility and user permissions
+    let visible_posts: Vec<Post> = user_posts
+        .into_iter()
+        .filter(|post| can_view_post(post, current_user.as_deref()))
+        .collect();
+    
+    // Sort by creation time (newest first)
+    let mut sorted_posts = visible_posts;
+    sorted_posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    
+    // Pinned posts come first, in pin order
+    let pinned_ids = state_handler::get_pinned_post_ids(user_id);
+    let (mut pinned_posts, unpinned_posts): (Vec<Post>, Vec<Post>) = sorted_posts
+        .into_iter()
+        .partition(|post| pinned_ids.contains(&post.post_id));
+    pinned_posts.sort_by_key(|post| pinned_ids.iter().position(|id| id == &post.post_id));
+    pinned_posts.extend(unpinned_posts);
+    let sorted_posts = pinned_posts;
+    
+    // Apply pagination
+    let start = offset as usize;
+    let end = start + limit as usize;
+    
+    if start >= sorted_posts.len() {
+        return Ok(Vec::new());
+    }
+    
+    let end = end.min(sorted_posts.len());
+    Ok(sorted_posts[start..end].to_vec())
+}
+
+const MAX_PINNED_POSTS: usize = 3;
+
+pub fn pin_post(post_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+
+    // Check if user owns the post
+    if post.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+
+    if post.is_archived {
+        return Err("Archived posts cannot be pinned".to_string());
+    }
+
+    let pinned_ids = state_handler::get_pinned_post_ids(&user_id);
+
+    if pinned_ids.contains(&post_id) {
+        return Err("Post is already pinned".to_string());
+    }
+
+    if pinned_ids.len() >= MAX_PINNED_POSTS {
+        return Err(format!("You can pin up to {} posts", MAX_PINNED_POSTS));
+    }
+
+    state_handler::pin_post(user_id, post_id.clone());
+
+    state_handler::get_post(&post_id).ok_or_else(|| "Post not found".to_string())
+}
+
+pub fn unpin_post(post_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+
+    // Check if user owns the post
+    if post.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+
+    if !state_handler::unpin_post(&user_id, &post_id) {
+        return Err("Post is not pinned".to_string());
+    }
+
+    state_handler::get_post(&post_id).ok_or_else(|| "Post not found".to_string())
+}
+
+pub fn archive_post(post_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+    
+    // Check if user owns the post
+    if post.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+    
+    // Archived posts leave the profile grid, so they can't stay pinned
+    state_handler::unpin_post(&user_id, &post_id);
+    
+    post.is_archived = true;
+    post.is_pinned = false;
+    post.updated_at = state_handler::get_current_timestamp();
+    
+    state_handler::insert_post(post_id, post.clone());
+    Ok(post)
+}
+
+pub fn unarchive_post(post_id: String) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut post = state_handler::get_post(&post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+    
+    // Check if user owns the post
+    if post.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+    
+    post.is_archived = false;
+    post.updated_at = state_handler::get_current_timestamp();
+    
+    state_handler::insert_post(post_id, post.clone());
+    Ok(post)
+}
+
+pub fn get_archived_posts(limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let user_posts = state_handler::get_user_posts(&user_id);
+    
+    // Filter for archived posts only
+    let archived_posts: Vec<Post> = user_posts
+        .into_iter()
+        .filter(|post| post.is_archived && post.user_id == user_id)
+        .collect();
+    
+    // Sort by creation time (newest first)
+    let mut sorted_posts = archived_posts;
+    sorted_posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    
+    // Apply pagination
+    let start = offset as usize;
+    let end = start + limit as usize;
+    
+    if start >= sorted_posts.len() {
+        return Ok(Vec::new());
+    }
+    
+    let end = end.min(sorted_posts.len());
+    Ok(sorted_posts[start..end].to_vec())
+}
+
+const STORY_LIFETIME: u64 = 24 * 60 * 60 * 1_000_000_000; // 24 hours in nanoseconds
+const MAX_STORED_STORY_VIEWERS: usize = 1000;
+const STORY_SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_ARCHIVE_RETENTION_DAYS: u32 = 365;
+const MAX_ARCHIVE_RETENTION_DAYS: u32 = 3650;
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// Timers don't survive upgrades, so this runs from both init and post_upgrade
+pub fn start_story_sweep_timer() {
+    ic_cdk_timers::set_timer_interval(STORY_SWEEP_INTERVAL, sweep_stories);
+}
+
+// Moves expired stories into their owner's archive and purges archived
+// stories past the owner's retention. Highlighted stories are always kept.
+fn sweep_stories() {
+    let current_time = state_handler::get_current_timestamp();
+
+    for story_id in state_handler::get_expired_story_ids(current_time) {
+        state_handler::archive_story(&story_id);
+    }
+
+    for story in state_handler::get_all_archived_stories() {
+        if story.is_highlight {
+            continue;
+        }
+
+        let retention = u64::from(get_archive_retention_days(&story.user_id)) * NANOS_PER_DAY;
+        if story.expires_at.saturating_add(retention) <= current_time {
+            state_handler::delete_story(&story.story_id);
+        }
+    }
+}
+
+fn get_archive_retention_days(user_id: &str) -> u32 {
+    state_handler::get_story_archive_settings(user_id)
+        .map_or(DEFAULT_ARCHIVE_RETENTION_DAYS, |settings| settings.retention_days)
+}
+
+pub fn update_story_archive_settings(retention_days: u32) -> Result<StoryArchiveSettings, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    if retention_days > MAX_ARCHIVE_RETENTION_DAYS {
+        return Err(format!(
+            "Archive retention must be {} days or less",
+            MAX_ARCHIVE_RETENTION_DAYS
+        ));
+    }
+
+    let settings = StoryArchiveSettings {
+        user_id,
+        retention_days,
+        updated_at: state_handler::get_current_timestamp(),
+    };
+
+    state_handler::update_story_archive_settings(settings.clone());
+    Ok(settings)
+}
+
+pub fn get_story_archive_settings() -> Result<StoryArchiveSettings, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    Ok(state_handler::get_story_archive_settings(&user_id).unwrap_or(StoryArchiveSettings {
+        user_id,
+        retention_days: DEFAULT_ARCHIVE_RETENTION_DAYS,
+        updated_at: 0,
+    }))
+}
+
+pub fn create_story(media: MediaItem, story_type: StoryType, text_overlay: Option<String>, stickers: Vec<Sticker>, music_info: Option<MusicInfo>, audience: Option<StoryAudience>, content_warning: Option<ContentWarning>) -> Result<Story, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let story_data = CreateStoryRequest {
+        media,
+        story_type,
+        text_overlay,
+        stickers,
+        music_info,
+        audience,
+        content_warning,
+    };
+    
+    create_story_for_user(&user_id, story_data)
+}
+
+pub fn validate_story_request(story_data: &CreateStoryRequest, user_id: &str) -> Result<(), String> {
+    match story_data.story_type {
+        StoryType::Photo if story_data.media.media_type != MediaType::Image => {
+            return Err("A photo story must use an image".to_string());
+        }
+        StoryType::Video if story_data.media.media_type != MediaType::Video => {
+            return Err("A video story must use a video".to_string());
+        }
+        _ => {}
+    }
+    
+    if let Some(music_info) = &story_data.music_info {
+        audio::validate_music_info(music_info)?;
+    }
+    
+    for sticker in &story_data.stickers {
+        validate_sticker(sticker)?;
+    }
+    
+    validate_media_item(&story_data.media, user_id)
+}
+
+const MAX_STICKER_TEXT_LENGTH: usize = 200;
+const MIN_POLL_OPTIONS: usize = 2;
+const MAX_POLL_OPTIONS: usize = 4;
+
+// Interactive stickers must carry a payload that matches their type
+fn validate_sticker(sticker: &Sticker) -> Result<(), String> {
+    let payload = match (&sticker.sticker_type, &sticker.payload) {
+        (StickerType::Poll, Some(payload @ StickerPayload::Poll { .. }))
+        | (StickerType::Question, Some(payload @ StickerPayload::Question { .. }))
+        | (StickerType::Quiz, Some(payload @ StickerPayload::Quiz { .. }))
+        | (StickerType::Slider, Some(payload @ StickerPayload::Slider { .. }))
+        | (StickerType::Countdown, Some(payload @ StickerPayload::Countdown { .. }))
+        | (StickerType::AddYours, Some(payload @ StickerPayload::AddYours { .. })) => payload,
+        (
+            StickerType::Poll
+            | StickerType::Question
+            | StickerType::Quiz
+            | StickerType::Slider
+            | StickerType::Countdown
+            | StickerType::AddYours,
+            _,
+        ) => return Err("Interactive stickers need a payload matching their type".to_string()),
+        (_, None) => return Ok(()),
+        (_, Some(_)) => return Err("This sticker type does not take a payload".to_string()),
+    };
+
+    let check_text = |text: &str| {
+        if text.trim().is_empty() {
+            return Err("Sticker text cannot be empty".to_string());
+        }
+        if text.len() > MAX_STICKER_TEXT_LENGTH {
+            return Err(format!(
+                "Sticker text must be {} characters or less",
+                MAX_STICKER_TEXT_LENGTH
+            ));
+        }
+        Ok(())
+    };
+
+    match payload {
+        StickerPayload::Poll { question, options } => {
+            check_text(question)?;
+            validate_sticker_options(options)?;
+            options.iter().try_for_each(|option| check_text(option))
+        }
+        StickerPayload::Quiz { question, options, correct_option } => {
+            check_text(question)?;
+            validate_sticker_options(options)?;
+            if *correct_option as usize >= options.len() {
+                return Err("Quiz answer must be one of the options".to_string());
+            }
+            options.iter().try_for_each(|option| check_text(option))
+        }
+        StickerPayload::Countdown { title, ends_at } => {
+            check_text(title)?;
+            if *ends_at <= state_handler::get_current_timestamp() {
+                return Err("Countdown must end in the future".to_string());
+            }
+            Ok(())
+        }
+        StickerPayload::Question { prompt } | StickerPayload::AddYours { prompt } => check_text(prompt),
+        StickerPayload::Slider { question, .. } => check_text(question),
+    }
+}
+
+fn validate_sticker_options(options: &[String]) -> Result<(), String> {
+    if options.len() < MIN_POLL_OPTIONS || options.len() > MAX_POLL_OPTIONS {
+        return Err(format!(
+            "Stickers need between {} and {} options",
+            MIN_POLL_OPTIONS, MAX_POLL_OPTIONS
+        ));
+    }
+    Ok(())
+}
+
+// Shared by direct and scheduled publishing
+pub fn create_story_for_user(user_id: &str, story_data: CreateStoryRequest) -> Result<Story, String> {
+    // Get user profile to get username
+    let user_profile = state_handler::get_user(user_id)
+        .ok_or_else(|| "User profile not found".to_string())?;
+    
+    validate_story_request(&story_data, user_id)?;
+    
+    let story_id = state_handler::generate_id();
+    let current_time = state_handler::get_current_timestamp();
+    let expires_at = current_time + STORY_LIFETIME;
+    
+    let mut story = Story {
+        story_id: story_id.clone(),
+        user_id: user_id.to_string(),
+        username: user_profile.username,
+        media: story_data.media,
+        story_type: story_data.story_type,
+        text_overlay: story_data.text_overlay,
+        stickers: story_data.stickers,
+        music_info: story_data.music_info,
+        viewers: Vec::new(),
+        view_count: 0,
+        created_at: current_time,
+        expires_at,
+        is_highlight: false,
+        highlight_id: None,
+        audience: story_data.audience.unwrap_or(StoryAudience::Everyone),
+        content_warning: story_data.content_warning,
+    };
+    
+    if let Some(music_info) = &mut story.music_info {
+        audio::record_audio_use(music_info, None)?;
+    }
+    
+    state_handler::retain_media_url(&story.media.url);
+    state_handler::insert_story(story_id, story.clone());
+    Ok(story)
+}
+
+pub fn view_story(story_id: String) -> Result<Story, String> {
+    let user_id = user_management::authenticate_user()?;
+    
+    let mut story = state_handler::get_story(&story_id)
+        .ok_or_else(|| "Story not found".to_string())?;
+    
+    if !can_view_story(&story, Some(&user_id)) {
+        return Err("Access denied".to_string());
+    }
+    
+    // Check if story has expired
+    if story.expires_at < state_handler::get_current_timestamp() && !story.is_highlight {
+        return Err("Story has expired".to_string());
+    }
+    
+    // Add viewer if not already in the list, keeping only the most recent ones.
+    // Viewers dropped from the list are remembered so they aren't counted twice.
+    if !story.viewers.contains(&user_id) {
+        if !state_handler::is_in_story_viewer_history(&story_id, &user_id) {
+            story.view_count += 1;
+        }
+        story.viewers.push(user_id);
+        if story.viewers.len() > MAX_STORED_STORY_VIEWERS {
+            let excess = story.viewers.len() - MAX_STORED_STORY_VIEWERS;
+            let dropped: Vec<String> = story.viewers.drain(..excess).collect();
+            state_handler::add_story_viewer_history(&story_id, &dropped);
+        }
+        state_handler::insert_story(story_id, story.clone());
+    }
+    
+    Ok(story)
+}
+
+pub fn get_user_stories(user_id: &str) -> Result<Vec<Story>, String> {
+    let current_user = user_management::authenticate_user().ok();
+    
+    // Check if user can view stories (not blocked, private account, hidden from)
+    if !can_view_profile_content(user_id, current_user.as_deref())
+        || current_user.as_deref().is_some_and(|viewer_id| state_handler::is_story_hidden_from(user_id, viewer_id))
+    {
+        return Err("Access denied".to_string());
+    }
+    
+    let stories = state_handler::get_user_stories(user_id);
+    let current_time = state_handler::get_current_timestamp();
+    
+    // Filter out expired stories and ones shared with a narrower audience
+    let active_stories: Vec<Story> = stories
+        .into_iter()
+        .filter(|story| story.expires_at > current_time || story.is_highlight)
+        .filter(|story| can_view_story(story, current_user.as_deref()))
+        .collect();
+    
+    Ok(active_stories)
+}
+
+pub fn respond_to_story_sticker(
+    story_id: String,
+    sticker_index: u32,
+    value: StickerResponseValue,
+) -> Result<StickerResponse, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let user_profile = state_handler::get_user(&user_id)
+        .ok_or_else(|| "User profile not found".to_string())?;
+
+    let story = state_handler::get_story(&story_id)
+        .ok_or_else(|| "Story not found".to_string())?;
+
+    if story.expires_at < state_handler::get_current_timestamp() && !story.is_highlight {
+        return Err("Story has expired".to_string());
+    }
+
+    if story.user_id == user_id {
+        return Err("You cannot respond to your own sticker".to_string());
+    }
+
+    if !can_view_story(&story, Some(&user_id)) {
+        return Err("Access denied".to_string());
+    }
+
+    let payload = story
+        .stickers
+        .get(sticker_index as usize)
+        .and_then(|sticker| sticker.payload.as_ref())
+        .ok_or_else(|| "Sticker does not accept responses".to_string())?;
+
+    match (payload, &value) {
+        (StickerPayload::Poll { options, .. }, StickerResponseValue::Vote(option))
+        | (StickerPayload::Quiz { options, .. }, StickerResponseValue::Vote(option)) => {
+            if *option as usize >= options.len() {
+                return Err("Option index out of range".to_string());
+            }
+        }
+        (StickerPayload::Question { .. }, StickerResponseValue::Answer(answer))
+        | (StickerPayload::AddYours { .. }, StickerResponseValue::Answer(answer)) => {
+            if answer.trim().is_empty() {
+                return Err("Answer cannot be empty".to_string());
+            }
+            if answer.len() > MAX_STICKER_TEXT_LENGTH {
+                return Err(format!(
+                    "Answer must be {} characters or less",
+                    MAX_STICKER_TEXT_LENGTH
+                ));
+            }
+        }
+        (StickerPayload::Slider { .. }, StickerResponseValue::Slide(position)) => {
+            if !(0.0..=1.0).contains(position) {
+                return Err("Slider value must be between 0 and 1".to_string());
+            }
+        }
+        (StickerPayload::Countdown { .. }, StickerResponseValue::Subscribe) => {}
+        _ => return Err("Response does not match the sticker type".to_string()),
+    }
+
+    // Each viewer gets a single response per sticker
+    if state_handler::has_user_responded_to_sticker(&story_id, sticker_index, &user_id) {
+        return Err("You have already responded to this sticker".to_string());
+    }
+
+    let response = StickerResponse {
+        story_id,
+        sticker_index,
+        user_id,
+        username: user_profile.username,
+        value,
+        created_at: state_handler::get_current_timestamp(),
+    };
+
+    state_handler::add_sticker_response(response.clone());
+    Ok(response)
+}
+
+pub fn get_story_sticker_results(story_id: String) -> Result<Vec<StickerResults>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let story = state_handler::get_story(&story_id)
+        .ok_or_else(|| "Story not found".to_string())?;
+
+    if story.user_id != user_id {
+        return Err("Only the story owner can see sticker results".to_string());
+    }
+
+    let responses = state_handler::get_sticker_responses(&story_id);
+
+    Ok(story
+        .stickers
+        .iter()
+        .enumerate()
+        .filter_map(|(index, sticker)| {
+            let payload = sticker.payload.as_ref()?;
+            let sticker_responses: Vec<&StickerResponse> = responses
+                .iter()
+                .filter(|response| response.sticker_index as usize == index)
+                .collect();
+            Some(aggregate_sticker_responses(
+                index as u32,
+                sticker.sticker_type.clone(),
+                payload,
+                &sticker_responses,
+            ))
+        })
+        .collect())
+}
+
+fn aggregate_sticker_responses(
+    sticker_index: u32,
+    sticker_type: StickerType,
+    payload: &StickerPayload,
+    responses: &[&StickerResponse],
+) -> StickerResults {
+    let mut results = StickerResults {
+        sticker_index,
+        sticker_type,
+        response_count: responses.len() as u64,
+        vote_counts: Vec::new(),
+        vote_percentages: Vec::new(),
+        correct_count: None,
+        average_slider_value: None,
+        answers: Vec::new(),
+        subscriber_count: 0,
+    };
+
+    match payload {
+        StickerPayload::Poll { options, .. } | StickerPayload::Quiz { options, .. } => {
+            let mut vote_counts = vec![0u64; options.len()];
+            for response in responses {
+                if let StickerResponseValue::Vote(option) = response.value {
+                    if let Some(count) = vote_counts.get_mut(option as usize) {
+                        *count += 1;
+                    }
+                }
+            }
+
+            let total_votes: u64 = vote_counts.iter().sum();
+            results.vote_percentages = vote_counts
+                .iter()
+                .map(|count| {
+                    if total_votes == 0 {
+                        0.0
+                    } else {
+                        *count as f32 * 100.0 / total_votes as f32
+                    }
+                })
+                .collect();
+
+            if let StickerPayload::Quiz { correct_option, .. } = payload {
+                results.correct_count = vote_counts.get(*correct_option as usize).copied();
+            }
+            results.vote_counts = vote_counts;
+        }
+        StickerPayload::Slider { .. } => {
+            let positions: Vec<f32> = responses
+                .iter()
+                .filter_map(|response| match response.value {
+                    StickerResponseValue::Slide(position) => Some(position),
+                    _ => None,
+                })
+                .collect();
+            if !positions.is_empty() {
+                results.average_slider_value =
+                    Some(positions.iter().sum::<f32>() / positions.len() as f32);
+            }
+        }
+        StickerPayload::Question { .. } | StickerPayload::AddYours { .. } => {
+            results.answers = responses.iter().map(|response| (*response).clone()).collect();
+        }
+        StickerPayload::Countdown { .. } => {
+            results.subscriber_count = responses.len() as u64;
+        }
+    }
+
+    results
+}
+
+pub fn get_story_archive() -> Result<Vec<Story>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let mut archived_stories = state_handler::get_user_archived_stories(&user_id);
+
+    // Sort by creation time (newest first)
+    archived_stories.sort_by_key(|story| std::cmp::Reverse(story.created_at));
+
+    Ok(archived_stories)
+}
+
+// Highlight operations
+const MAX_HIGHLIGHT_TITLE_LENGTH: usize = 16;
+const MAX_HIGHLIGHT_STORIES: usize = 100;
+
+pub fn create_highlight(title: String, story_ids: Vec<String>) -> Result<Highlight, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    validate_highlight_title(&title)?;
+
+    if story_ids.is_empty() {
+        return Err("Highlight must contain at least one story".to_string());
+    }
+
+    let stories = get_archived_stories_for_highlight(&user_id, &story_ids)?;
+
+    let highlight_id = state_handler::generate_id();
+    let current_time = state_handler::get_current_timestamp();
+
+    let highlight = Highlight {
+        highlight_id: highlight_id.clone(),
+        user_id: user_id.clone(),
+        title: title.trim().to_string(),
+        cover_image: stories[0].media.url.clone(),
+        stories: story_ids,
+        created_at: current_time,
+        updated_at: current_time,
+    };
+
+    mark_stories_as_highlight(stories, &highlight_id);
+    state_handler::insert_highlight(highlight_id, highlight.clone());
+
+    Ok(highlight)
+}
+
+pub fn rename_highlight(highlight_id: String, title: String) -> Result<Highlight, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let mut highlight = get_owned_highlight(&highlight_id, &user_id)?;
+
+    validate_highlight_title(&title)?;
+
+    highlight.title = title.trim().to_string();
+    highlight.updated_at = state_handler::get_current_timestamp();
+
+    state_handler::insert_highlight(highlight_id, highlight.clone());
+    Ok(highlight)
+}
+
+pub fn reorder_highlights(highlight_ids: Vec<String>) -> Result<Vec<Highlight>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let current_ids: Vec<String> = state_handler::get_user_highlights(&user_id)
+        .into_iter()
+        .map(|highlight| highlight.highlight_id)
+        .collect();
+
+    // The new order must be a permutation of the user's existing highlights
+    let mut sorted_current = current_ids.clone();
+    let mut sorted_new = highlight_ids.clone();
+    sorted_current.sort();
+    sorted_new.sort();
+    if sorted_current != sorted_new {
+        return Err("Highlight order must include each of your highlights exactly once".to_string());
+    }
+
+    state_handler::set_user_highlight_order(user_id.clone(), highlight_ids);
+
+    Ok(state_handler::get_user_highlights(&user_id))
+}
+
+pub fn delete_highlight(highlight_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+
+    get_owned_highlight(&highlight_id, &user_id)?;
+
+    state_handler::delete_highlight(&highlight_id)
+}
+
+pub fn add_stories_to_highlight(highlight_id: String, story_ids: Vec<String>) -> Result<Highlight, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let mut highlight = get_owned_highlight(&highlight_id, &user_id)?;
+
+    if story_ids.is_empty() {
+        return Err("No stories provided".to_string());
+    }
+
+    if story_ids.iter().any(|story_id| highlight.stories.contains(story_id)) {
+        return Err("Story is already in this highlight".to_string());
+    }
+
+    if highlight.stories.len() + story_ids.len() > MAX_HIGHLIGHT_STORIES {
+        return Err(format!("Highlights can contain at most {} stories", MAX_HIGHLIGHT_STORIES));
+    }
+
+    let stories = get_archived_stories_for_highlight(&user_id, &story_ids)?;
+
+    mark_stories_as_highlight(stories, &highlight_id);
+
+    highlight.stories.extend(story_ids);
+    highlight.updated_at = state_handler::get_current_timestamp();
+
+    state_handler::insert_highlight(highlight_id, highlight.clone());
+    Ok(highlight)
+}
+
+pub fn remove_story_from_highlight(highlight_id: String, story_id: String) -> Result<Highlight, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let mut highlight = get_owned_highlight(&highlight_id, &user_id)?;
+
+    if !highlight.stories.contains(&story_id) {
+        return Err("Story is not in this highlight".to_string());
+    }
+
+    if highlight.stories.len() == 1 {
+        return Err("Highlight must contain at least one story; delete the highlight instead".to_string());
+    }
+
+    highlight.stories.retain(|id| id != &story_id);
+
+    if let Some(mut story) = state_handler::get_story(&story_id) {
+        // Fall back to the first remaining story if the cover came from this one
+        if highlight.cover_image == story.media.url {
+            if let Some(first_story) = state_handler::get_story(&highlight.stories[0]) {
+                highlight.cover_image = first_story.media.url;
+            }
+        }
+
+        story.is_highlight = false;
+        story.highlight_id = None;
+        state_handler::insert_story(story_id, story);
+    }
+
+    highlight.updated_at = state_handler::get_current_timestamp();
+
+    state_handler::insert_highlight(highlight_id, highlight.clone());
+    Ok(highlight)
+}
+
+pub fn set_highlight_cover(highlight_id: String, story_id: String) -> Result<Highlight, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let mut highlight = get_owned_highlight(&highlight_id, &user_id)?;
+
+    if !highlight.stories.contains(&story_id) {
+        return Err("Cover must be one of the highlight's stories".to_string());
+    }
+
+    let story = state_handler::get_story(&story_id)
+        .ok_or_else(|| "Story not found".to_string())?;
+
+    highlight.cover_image = story.media.url;
+    highlight.updated_at = state_handler::get_current_timestamp();
+
+    state_handler::insert_highlight(highlight_id, highlight.clone());
+    Ok(highlight)
+}
+
+pub fn get_user_highlights(user_id: &str) -> Result<Vec<Highlight>, String> {
+    let current_user = user_management::authenticate_user().ok();
+
+    if !state_handler::user_exists(user_id) {
+        return Err("User not found".to_string());
+    }
+
+    if !can_view_profile_content(user_id, current_user.as_deref()) {
+        return Err("Access denied".to_string());
+    }
+
+    Ok(state_handler::get_user_highlights(user_id))
+}
+
+pub fn get_highlight_stories(highlight_id: String) -> Result<Vec<Story>, String> {
+    let current_user = user_management::authenticate_user().ok();
+
+    let highlight = state_handler::get_highlight(&highlight_id)
+        .ok_or_else(|| "Highlight not found".to_string())?;
+
+    if !can_view_profile_content(&highlight.user_id, current_user.as_deref()) {
+        return Err("Access denied".to_string());
+    }
+
+    Ok(highlight
+        .stories
+        .iter()
+        .filter_map(|story_id| state_handler::get_story(story_id))
+        .filter(|story| can_view_story(story, current_user.as_deref()))
+        .collect())
+}
+
+fn validate_highlight_title(title: &str) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("Highlight title cannot be empty".to_string());
+    }
+
+    if title.trim().chars().count() > MAX_HIGHLIGHT_TITLE_LENGTH {
+        return Err(format!(
+            "Highlight title must be {} characters or less",
+            MAX_HIGHLIGHT_TITLE_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
+fn get_owned_highlight(highlight_id: &str, user_id: &str) -> Result<Highlight, String> {
+    let highlight = state_handler::get_highlight(highlight_id)
+        .ok_or_else(|| "Highlight not found".to_string())?;
+
+    // Check if user owns the highlight
+    if highlight.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+
+    Ok(highlight)
+}
+
+// Highlights are built from the owner's story archive, i.e. stories that have
+// already expired and are not part of another highlight
+fn get_archived_stories_for_highlight(user_id: &str, story_ids: &[String]) -> Result<Vec<Story>, String> {
+    let current_time = state_handler::get_current_timestamp();
+    let mut stories = Vec::new();
+
+    for (index, story_id) in story_ids.iter().enumerate() {
+        if story_ids[..index].contains(story_id) {
+            return Err("Duplicate story in request".to_string());
+        }
+
+        let story = state_handler::get_story(story_id)
+            .ok_or_else(|| format!("Story {} not found", story_id))?;
+
+        if story.user_id != user_id {
+            return Err("Access denied".to_string());
+        }
+
+        if story.expires_at > current_time {
+            return Err("Only stories from your archive can be added to highlights".to_string());
+        }
+
+        if story.is_highlight {
+            return Err(format!("Story {} is already in a highlight", story_id));
+        }
+
+        stories.push(story);
+    }
+
+    Ok(stories)
+}
+
+fn mark_stories_as_highlight(stories: Vec<Story>, highlight_id: &str) {
+    for mut story in stories {
+        story.is_highlight = true;
+        story.highlight_id = Some(highlight_id.to_string());
+        state_handler::insert_story(story.story_id.clone(), story);
+    }
+}
+
+// Stories add the owner's hide-from list and close friends audience on top
+// of the profile rules
+pub fn can_view_story(story: &Story, current_user_id: Option<&str>) -> bool {
+    if current_user_id == Some(story.user_id.as_str()) {
+        return true;
+    }
+
+    if !can_view_profile_content(&story.user_id, current_user_id) {
+        return false;
+    }
+
+    let Some(user_id) = current_user_id else {
+        return story.audience == StoryAudience::Everyone;
+    };
+
+    if state_handler::is_story_hidden_from(&story.user_id, user_id) {
+        return false;
+    }
+
+    match story.audience {
+        StoryAudience::Everyone => true,
+        StoryAudience::CloseFriends => state_handler::is_close_friend(&story.user_id, user_id),
+    }
+}
+
+pub fn update_story_reply_setting(setting: StoryReplySetting) -> Result<StoryReplySetting, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    state_handler::set_story_reply_setting(user_id, setting.clone());
+    Ok(setting)
+}
+
+pub fn get_story_reply_setting() -> Result<StoryReplySetting, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    Ok(state_handler::get_story_reply_setting(&user_id))
+}
+
+// Replies and reactions both go through the owner's reply setting
+pub fn check_story_reply_allowed(story: &Story, user_id: &str) -> Result<(), String> {
+    if story.user_id == user_id {
+        return Err("You cannot reply to your own story".to_string());
+    }
+
+    if !can_view_story(story, Some(user_id)) {
+        return Err("Access denied".to_string());
+    }
+
+    if story.expires_at <= state_handler::get_current_timestamp() && !story.is_highlight {
+        return Err("Story has expired".to_string());
+    }
+
+    let allowed = match state_handler::get_story_reply_setting(&story.user_id) {
+        StoryReplySetting::Everyone => true,
+        StoryReplySetting::Followers => state_handler::is_following(user_id, &story.user_id),
+        StoryReplySetting::Following => state_handler::is_following(&story.user_id, user_id),
+        StoryReplySetting::Off => false,
+    };
+
+    if !allowed {
+        return Err("Replies to this story are turned off for you".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn hide_story_from(hidden_user_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+
+    if hidden_user_id == user_id {
+        return Err("You cannot hide your story from yourself".to_string());
+    }
+
+    if !state_handler::user_exists(&hidden_user_id) {
+        return Err("User not found".to_string());
+    }
+
+    if state_handler::is_story_hidden_from(&user_id, &hidden_user_id) {
+        return Err("Your story is already hidden from this user".to_string());
+    }
+
+    state_handler::hide_stories_from(user_id, hidden_user_id);
+    Ok(())
+}
+
+pub fn unhide_story_from(hidden_user_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+
+    if !state_handler::unhide_stories_from(&user_id, &hidden_user_id) {
+        return Err("Your story is not hidden from this user".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn get_story_hidden_from() -> Result<Vec<UserProfile>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    Ok(state_handler::get_story_hidden_from(&user_id)
+        .iter()
+        .filter_map(|hidden_user_id| state_handler::get_user(hidden_user_id))
+        .collect())
+}
+
+// Profile-level content (stories, highlights) follows the account's privacy and blocks
+pub fn can_view_profile_content(owner_id: &str, current_user_id: Option<&str>) -> bool {
+    if let Some(user_id) = current_user_id {
+        if user_id == owner_id {
+            return true;
+        }
+
+        if user_management::is_user_blocked(owner_id, user_id)
+            || user_management::is_user_blocked(user_id, owner_id)
+        {
+            return false;
+        }
+    }
+
+    match state_handler::get_user(owner_id) {
+        Some(owner) if owner.is_private => current_user_id
+            .map(|user_id| state_handler::is_following(user_id, owner_id))
+            .unwrap_or(false),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+pub fn can_view_post(post: &Post, current_user_id: Option<&str>) -> bool {
+    if current_user_id.is_some_and(|user_id| post.is_author(user_id)) {
+        return true;
+    }
+    
+    match post.visibility {
+        PostVisibility::Public => true,
         PostVisibility::Private => {
             if let Some(user_id) = current_user_id {
                 user_id == post.user_id
@@ -371,29 +2169,11 @@ pub fn get_trending_posts(limit: u32) -> Result<Vec<Post>, String> {
 }
 
 pub fn get_posts_by_hashtag(hashtag: &str, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
-    // This would be implemented with proper hashtag indexing in a real system
-    let all_posts = state_handler::get_all_posts();
+    let current_user = user_management::authenticate_user().ok();
     
-    let hashtag_posts: Vec<Post> = all_posts
+    Ok(discovery::get_recent_hashtag_posts(hashtag, current_user.as_deref(), offset as usize + limit as usize)
         .into_iter()
-        .filter(|post| {
-            post.hashtags.iter().any(|h| h.to_lowercase() == hashtag.to_lowercase()) &&
-            matches!(post.visibility, PostVisibility::Public)
-        })
-        .collect();
-    
-    // Sort by creation time (newest first)
-    let mut sorted_posts = hashtag_posts;
-    sorted_posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-    
-    // Apply pagination
-    let start = offset as usize;
-    let end = start + limit as usize;
-    
-    if start >= sorted_posts.len() {
-        return Ok(Vec::new());
-    }
-    
-    let end = end.min(sorted_posts.len());
-    Ok(sorted_posts[start..end].to_vec())
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect())
 }
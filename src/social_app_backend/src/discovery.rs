@@ -1,29 +1,33 @@
+use crate::audio;
+use crate::safety_privacy;
 use crate::state_handler;
 use crate::types::*;
 use crate::user_management;
 
 pub fn search_content(query: String, search_type: SearchType) -> Result<SearchResults, String> {
-    let _user_id = user_management::authenticate_user().ok();
+    let user_id = user_management::authenticate_user().ok();
     
     let mut results = SearchResults {
         users: Vec::new(),
         posts: Vec::new(),
         hashtags: Vec::new(),
         locations: Vec::new(),
+        audio_tracks: Vec::new(),
     };
     
     match search_type {
         SearchType::All => {
             results.users = search_users(&query, 10);
-            results.posts = search_posts(&query, 10);
+            results.posts = search_posts(&query, 10, user_id.as_deref());
             results.hashtags = search_hashtags(&query, 10);
             results.locations = search_locations(&query, 10);
+            results.audio_tracks = audio::search_audio(&query, 10);
         },
         SearchType::Users => {
             results.users = search_users(&query, 50);
         },
         SearchType::Posts => {
-            results.posts = search_posts(&query, 50);
+            results.posts = search_posts(&query, 50, user_id.as_deref());
         },
         SearchType::Hashtags => {
             results.hashtags = search_hashtags(&query, 50);
@@ -32,31 +36,13 @@ pub fn search_content(query: String, search_type: SearchType) -> Result<SearchRe
             results.locations = search_locations(&query, 50);
         },
         SearchType::Audio => {
-            // Search for posts with audio content
-            results.posts = search_audio_posts(&query, 50);
+            results.audio_tracks = audio::search_audio(&query, 50);
         }
     }
     
     Ok(results)
 }
 
-// Helper function for audio search
-fn search_audio_posts(query: &str, limit: usize) -> Vec<Post> {
-    let all_posts = state_handler::get_all_posts();
-    
-    let matching_posts: Vec<Post> = all_posts
-        .into_iter()
-        .filter(|post| {
-            matches!(post.visibility, PostVisibility::Public) &&
-            (post.caption.to_lowercase().contains(&query.to_lowercase()) ||
-             post.hashtags.iter().any(|h| h.to_lowercase().contains(&query.to_lowercase())))
-        })
-        .take(limit)
-        .collect();
-    
-    matching_posts
-}
-
 pub fn get_explore_content(limit: u32) -> Result<Vec<Post>, String> {
     let current_user = user_management::authenticate_user().ok();
 
@@ -65,6 +51,9 @@ pub fn get_explore_content(limit: u32) -> Result<Vec<Post>, String> {
     let public_posts: Vec<Post> = all_posts
         .into_iter()
         .filter(|post| matches!(post.visibility, PostVisibility::Public))
+        .filter(|post| {
+            !safety_privacy::is_hidden_from_discovery(post.content_warning.as_ref(), current_user.as_deref())
+        })
         .collect();
 
     // Sort by engagement and recency
@@ -87,6 +76,151 @@ pub fn get_explore_content(limit: u32) -> Result<Vec<Post>, String> {
     Ok(explore_posts)
 }
 
+const MAX_FOLLOWED_HASHTAGS: usize = 500;
+const MAX_RELATED_HASHTAGS: usize = 10;
+// Co-occurrence is measured over this many of the hashtag's newest posts
+const RELATED_HASHTAG_SAMPLE: usize = 200;
+// The Top tab ranks this many of the hashtag's newest posts
+const TOP_HASHTAG_WINDOW: usize = 500;
+
+pub fn follow_hashtag(hashtag: String) -> Result<Hashtag, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let hashtag = state_handler::get_hashtag(&hashtag)
+        .ok_or_else(|| "Hashtag not found".to_string())?;
+
+    let followed = state_handler::get_followed_hashtags(&user_id);
+
+    if followed.contains(&hashtag.name) {
+        return Err("You already follow this hashtag".to_string());
+    }
+
+    if followed.len() >= MAX_FOLLOWED_HASHTAGS {
+        return Err(format!("You can follow up to {} hashtags", MAX_FOLLOWED_HASHTAGS));
+    }
+
+    state_handler::follow_hashtag(user_id, hashtag.name.clone());
+    Ok(hashtag)
+}
+
+pub fn unfollow_hashtag(hashtag: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+
+    if !state_handler::unfollow_hashtag(&user_id, &state_handler::normalize_hashtag(&hashtag)) {
+        return Err("You don't follow this hashtag".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn get_followed_hashtags() -> Result<Vec<Hashtag>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    Ok(state_handler::get_followed_hashtags(&user_id)
+        .iter()
+        .filter_map(|hashtag| state_handler::get_hashtag(hashtag))
+        .collect())
+}
+
+// The most recently indexed public posts for the hashtag, at most `limit`,
+// newest first
+pub fn get_recent_hashtag_posts(hashtag: &str, current_user_id: Option<&str>, limit: usize) -> Vec<Post> {
+    let mut posts = state_handler::get_recent_hashtag_posts(hashtag, limit, |post| {
+        is_visible_hashtag_post(post, current_user_id)
+    });
+
+    posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+    posts
+}
+
+fn is_visible_hashtag_post(post: &Post, current_user_id: Option<&str>) -> bool {
+    matches!(post.visibility, PostVisibility::Public)
+        && !post.is_archived
+        && current_user_id.is_none_or(|user_id| {
+            !user_management::is_user_blocked(&post.user_id, user_id)
+                && !user_management::is_user_blocked(user_id, &post.user_id)
+        })
+        && !safety_privacy::is_hidden_from_discovery(post.content_warning.as_ref(), current_user_id)
+}
+
+pub fn get_hashtag_page(
+    hashtag: String,
+    tab: HashtagTab,
+    limit: u32,
+    offset: u32,
+) -> Result<HashtagPage, String> {
+    let current_user = user_management::authenticate_user().ok();
+
+    let hashtag = state_handler::get_hashtag(&hashtag)
+        .ok_or_else(|| "Hashtag not found".to_string())?;
+
+    let sample = get_recent_hashtag_posts(&hashtag.name, current_user.as_deref(), RELATED_HASHTAG_SAMPLE);
+    let related_hashtags = get_related_hashtags(&hashtag.name, &sample);
+
+    // Recent only needs the newest offset + limit posts; Top ranks a bounded
+    // window of recent posts
+    let posts = match tab {
+        HashtagTab::Recent => get_recent_hashtag_posts(
+            &hashtag.name,
+            current_user.as_deref(),
+            offset as usize + limit as usize,
+        ),
+        HashtagTab::Top => {
+            let mut posts = get_recent_hashtag_posts(&hashtag.name, current_user.as_deref(), TOP_HASHTAG_WINDOW);
+            posts.sort_by(|a, b| {
+                calculate_explore_score(b)
+                    .partial_cmp(&calculate_explore_score(a))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            posts
+        }
+    };
+
+    let is_following = current_user.as_deref().is_some_and(|user_id| {
+        state_handler::get_followed_hashtags(user_id).contains(&hashtag.name)
+    });
+
+    Ok(HashtagPage {
+        hashtag,
+        posts: posts
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect(),
+        related_hashtags,
+        is_following,
+    })
+}
+
+// Hashtags that most often appear alongside this one on recent posts
+fn get_related_hashtags(hashtag: &str, recent_posts: &[Post]) -> Vec<Hashtag> {
+    let mut co_occurrences: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
+
+    for post in recent_posts {
+        let mut seen = Vec::new();
+        for other in &post.hashtags {
+            let other = state_handler::normalize_hashtag(other);
+            if other != hashtag && !seen.contains(&other) {
+                *co_occurrences.entry(other.clone()).or_default() += 1;
+                seen.push(other);
+            }
+        }
+    }
+
+    let mut related: Vec<(u64, Hashtag)> = co_occurrences
+        .into_iter()
+        .filter_map(|(name, count)| state_handler::get_hashtag(&name).map(|tag| (count, tag)))
+        .collect();
+
+    related.sort_by_key(|(count, tag)| (std::cmp::Reverse(*count), std::cmp::Reverse(tag.posts_count)));
+
+    related
+        .into_iter()
+        .take(MAX_RELATED_HASHTAGS)
+        .map(|(_, tag)| tag)
+        .collect()
+}
+
 pub fn get_trending_hashtags(limit: u32) -> Result<Vec<Hashtag>, String> {
     let hashtags = state_handler::search_hashtags("");
 
@@ -261,7 +395,7 @@ fn search_users(query: &str, limit: usize) -> Vec<UserProfile> {
     state_handler::search_users(query, limit)
 }
 
-fn search_posts(query: &str, limit: usize) -> Vec<Post> {
+fn search_posts(query: &str, limit: usize, current_user_id: Option<&str>) -> Vec<Post> {
     let all_posts = state_handler::get_all_posts();
 
     let matching_posts: Vec<Post> = all_posts
@@ -274,6 +408,7 @@ fn search_posts(query: &str, limit: usize) -> Vec<Post> {
                         .iter()
                         .any(|h| h.to_lowercase().contains(&query.to_lowercase())))
         })
+        .filter(|post| !safety_privacy::is_hidden_from_discovery(post.content_warning.as_ref(), current_user_id))
         .take(limit)
         .collect();
 
@@ -347,7 +482,7 @@ fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
 
 fn calculate_explore_score(post: &Post) -> f64 {
     let current_time = state_handler::get_current_timestamp();
-    let age_hours = (current_time - post.created_at) as f64 / (1000.0 * 60.0 * 60.0);
+    let age_hours = current_time.saturating_sub(post.created_at) as f64 / (1_000_000_000.0 * 60.0 * 60.0);
 
     // Calculate engagement score
     let engagement = post.likes_count + post.comments_count + (post.shares_count * 2);
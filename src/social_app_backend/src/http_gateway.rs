@@ -0,0 +1,437 @@
+use crate::content_management;
+use crate::media_storage::CHUNK_SIZE;
+use crate::profile_management;
+use crate::state_handler;
+use crate::types::*;
+use serde_json::json;
+
+// Keeps a single response comfortably under the query response size limit
+const MAX_RANGE_LENGTH: u64 = 2 * CHUNK_SIZE;
+const MAX_PROFILE_POSTS: usize = 12;
+// Kept short so media stops being served from caches soon after the post or
+// account it belongs to stops being public
+const MEDIA_CACHE_CONTROL: &str = "public, max-age=300, must-revalidate";
+const PAGE_CACHE_CONTROL: &str = "public, max-age=60";
+const ERROR_CACHE_CONTROL: &str = "no-cache";
+
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    let is_head = request.method.eq_ignore_ascii_case("HEAD");
+    if !is_head && !request.method.eq_ignore_ascii_case("GET") {
+        return text_response(405, "Method not allowed");
+    }
+
+    let path = request.url.split(['?', '#']).next().unwrap_or("/");
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let mut response = match segments.as_slice() {
+        ["media", hash] => serve_media(hash, &request),
+        ["u", username] => match username.strip_suffix(".json") {
+            Some(username) => profile_json(username),
+            None => profile_html(username),
+        },
+        ["p", post_id] => match post_id.strip_suffix(".json") {
+            Some(post_id) => post_json(post_id),
+            None => post_html(post_id),
+        },
+        _ => text_response(404, "Not found"),
+    };
+
+    if is_head {
+        response.body.clear();
+        response.streaming_strategy = None;
+    }
+
+    response
+}
+
+pub fn http_request_streaming_callback(token: StreamingCallbackToken) -> StreamingCallbackHttpResponse {
+    let Some(media) = get_public_media(&token.hash) else {
+        return StreamingCallbackHttpResponse { body: Vec::new(), token: None };
+    };
+
+    let body = state_handler::get_media_chunk(&token.hash, token.chunk_index).unwrap_or_default();
+    let next_index = token.chunk_index + 1;
+
+    StreamingCallbackHttpResponse {
+        body,
+        token: (next_index < media.chunk_count).then_some(StreamingCallbackToken {
+            hash: token.hash,
+            chunk_index: next_index,
+        }),
+    }
+}
+
+fn serve_media(hash: &str, request: &HttpRequest) -> HttpResponse {
+    let Some(media) = get_public_media(hash) else {
+        return text_response(404, "Media not found");
+    };
+
+    let etag = format!("\"{}\"", media.hash);
+    if header_value(request, "if-none-match").is_some_and(|value| value == etag) {
+        return HttpResponse {
+            status_code: 304,
+            headers: vec![
+                ("ETag".to_string(), etag),
+                ("Cache-Control".to_string(), MEDIA_CACHE_CONTROL.to_string()),
+            ],
+            body: Vec::new(),
+            streaming_strategy: None,
+        };
+    }
+
+    let mut headers = vec![
+        ("Content-Type".to_string(), media.mime_type.clone()),
+        ("Accept-Ranges".to_string(), "bytes".to_string()),
+        ("ETag".to_string(), etag),
+        ("Cache-Control".to_string(), MEDIA_CACHE_CONTROL.to_string()),
+    ];
+
+    if let Some(range_header) = header_value(request, "range") {
+        let Some((start, end)) = parse_range(range_header, media.size) else {
+            headers.push(("Content-Range".to_string(), format!("bytes */{}", media.size)));
+            return HttpResponse {
+                status_code: 416,
+                headers,
+                body: Vec::new(),
+                streaming_strategy: None,
+            };
+        };
+
+        // Large ranges are shortened; clients continue from Content-Range
+        let end = end.min(start + MAX_RANGE_LENGTH - 1);
+        let body = read_media_range(&media, start, end);
+
+        headers.push(("Content-Range".to_string(), format!("bytes {}-{}/{}", start, end, media.size)));
+        headers.push(("Content-Length".to_string(), body.len().to_string()));
+
+        return HttpResponse {
+            status_code: 206,
+            headers,
+            body,
+            streaming_strategy: None,
+        };
+    }
+
+    // Full responses start with the first chunk and stream the rest
+    headers.push(("Content-Length".to_string(), media.size.to_string()));
+
+    HttpResponse {
+        status_code: 200,
+        headers,
+        body: state_handler::get_media_chunk(&media.hash, 0).unwrap_or_default(),
+        streaming_strategy: (media.chunk_count > 1).then(|| StreamingStrategy::Callback {
+            callback: StreamingCallback::new(ic_cdk::id(), "http_request_streaming_callback".to_string()),
+            token: StreamingCallbackToken {
+                hash: media.hash.clone(),
+                chunk_index: 1,
+            },
+        }),
+    }
+}
+
+// Supports a single range: "bytes=start-end", "bytes=start-" or "bytes=-suffix"
+fn parse_range(header: &str, size: u64) -> Option<(u64, u64)> {
+    let range = header.trim().strip_prefix("bytes=")?;
+    if range.contains(',') || size == 0 {
+        return None;
+    }
+
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return None;
+            }
+            (size.saturating_sub(suffix), size - 1)
+        }
+        (start, "") => (start.parse().ok()?, size - 1),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(size - 1)),
+    };
+
+    (start <= end && start < size).then_some((start, end))
+}
+
+fn read_media_range(media: &StoredMedia, start: u64, end: u64) -> Vec<u8> {
+    let first_chunk = (start / CHUNK_SIZE) as u32;
+    let last_chunk = (end / CHUNK_SIZE) as u32;
+    let mut body = Vec::with_capacity((end - start + 1) as usize);
+
+    for chunk_index in first_chunk..=last_chunk {
+        let chunk = state_handler::get_media_chunk(&media.hash, chunk_index).unwrap_or_default();
+        let chunk_start = chunk_index as u64 * CHUNK_SIZE;
+        let from = start.saturating_sub(chunk_start) as usize;
+        let to = ((end + 1 - chunk_start) as usize).min(chunk.len());
+        if from < to {
+            body.extend_from_slice(&chunk[from..to]);
+        }
+    }
+
+    body
+}
+
+fn header_value<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    request
+        .headers
+        .iter()
+        .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+// Only public posts by public accounts are exposed over HTTP
+fn get_public_post(post_id: &str) -> Option<(Post, UserProfile)> {
+    let post = state_handler::get_post(post_id)?;
+    let author = state_handler::get_user(&post.user_id)?;
+
+    let is_public = matches!(post.visibility, PostVisibility::Public) && !post.is_archived && !author.is_private;
+    is_public.then(|| (content_management::prepare_post_for_viewer(post, None), author))
+}
+
+// Media is served (and cached) only while a public post or a public account's
+// profile picture uses it
+fn get_public_media(hash: &str) -> Option<StoredMedia> {
+    let media = state_handler::get_media(hash)?;
+
+    let is_public_author = |user_id: &str| state_handler::get_user(user_id).is_some_and(|user| !user.is_private);
+
+    let in_public_post = state_handler::get_posts_with_media(&media.url).iter().any(|post| {
+        matches!(post.visibility, PostVisibility::Public) && !post.is_archived && is_public_author(&post.user_id)
+    });
+    let is_public_picture = state_handler::get_users_with_profile_picture(&media.url)
+        .iter()
+        .any(|user| !user.is_private);
+
+    (in_public_post || is_public_picture).then_some(media)
+}
+
+fn get_public_profile_posts(user_profile: &UserProfile) -> Vec<Post> {
+    if user_profile.is_private {
+        return Vec::new();
+    }
+
+    let mut posts: Vec<Post> = state_handler::get_posts_by_user(&user_profile.user_id)
+        .into_iter()
+        .filter(|post| matches!(post.visibility, PostVisibility::Public) && !post.is_archived)
+        .map(|post| content_management::prepare_post_for_viewer(post, None))
+        .collect();
+
+    posts.sort_by_key(|post| std::cmp::Reverse(post.created_at));
+    posts.truncate(MAX_PROFILE_POSTS);
+    posts
+}
+
+fn profile_to_json(user_profile: &UserProfile) -> serde_json::Value {
+    json!({
+        "user_id": user_profile.user_id,
+        "username": user_profile.username,
+        "display_name": user_profile.display_name,
+        "bio": user_profile.bio,
+        "profile_picture": user_profile.profile_picture,
+        "website": user_profile.website,
+        "is_verified": user_profile.is_verified,
+        "is_private": user_profile.is_private,
+        "followers_count": user_profile.followers_count,
+        "following_count": user_profile.following_count,
+        "posts_count": user_profile.posts_count,
+        "business_profile": profile_management::public_business_profile(user_profile),
+    })
+}
+
+fn post_to_json(post: &Post) -> serde_json::Value {
+    let media: Vec<serde_json::Value> = post
+        .media
+        .iter()
+        .map(|item| {
+            json!({
+                "url": item.url,
+                "media_type": format!("{:?}", item.media_type),
+                "width": item.width,
+                "height": item.height,
+                "alt_text": item.alt_text,
+            })
+        })
+        .collect();
+
+    let collaborators: Vec<&str> = post
+        .collaborators
+        .iter()
+        .filter(|collaborator| collaborator.status == CollaboratorStatus::Accepted)
+        .map(|collaborator| collaborator.username.as_str())
+        .collect();
+
+    json!({
+        "post_id": post.post_id,
+        "username": post.username,
+        "collaborators": collaborators,
+        "content_type": format!("{:?}", post.content_type),
+        "caption": post.caption,
+        "hashtags": post.hashtags,
+        "media": media,
+        "content_warning": post.content_warning.as_ref().map(|warning| format!("{:?}", warning)),
+        "is_blurred": post.is_blurred,
+        "likes_count": (!post.hide_like_count).then_some(post.likes_count),
+        "comments_count": post.comments_count,
+        "created_at": post.created_at,
+    })
+}
+
+fn profile_json(username: &str) -> HttpResponse {
+    let Some(user_profile) = state_handler::get_user_by_username(username) else {
+        return json_response(404, json!({ "error": "User not found" }), ERROR_CACHE_CONTROL);
+    };
+
+    let posts: Vec<serde_json::Value> = get_public_profile_posts(&user_profile).iter().map(post_to_json).collect();
+
+    let mut body = profile_to_json(&user_profile);
+    body["posts"] = json!(posts);
+
+    json_response(200, body, PAGE_CACHE_CONTROL)
+}
+
+fn post_json(post_id: &str) -> HttpResponse {
+    match get_public_post(post_id) {
+        Some((post, _)) => json_response(200, post_to_json(&post), PAGE_CACHE_CONTROL),
+        None => json_response(404, json!({ "error": "Post not found" }), ERROR_CACHE_CONTROL),
+    }
+}
+
+fn profile_html(username: &str) -> HttpResponse {
+    let Some(user_profile) = state_handler::get_user_by_username(username) else {
+        return text_response(404, "User not found");
+    };
+
+    let title = format!("{} (@{})", user_profile.display_name, user_profile.username);
+    let mut content = format!(
+        "<h1>{}</h1><p>{}</p><p>{} posts · {} followers · {} following</p>",
+        escape_html(&title),
+        escape_html(&user_profile.bio),
+        user_profile.posts_count,
+        user_profile.followers_count,
+        user_profile.following_count,
+    );
+
+    if user_profile.is_private {
+        content.push_str("<p>This account is private.</p>");
+    } else {
+        for post in get_public_profile_posts(&user_profile) {
+            content.push_str(&format!(
+                "<article><a href=\"/p/{}\">{}</a></article>",
+                escape_html(&post.post_id),
+                media_preview_html(&post),
+            ));
+        }
+    }
+
+    // Private accounts' pictures aren't served over HTTP
+    let image = (!user_profile.is_private)
+        .then_some(user_profile.profile_picture.as_deref())
+        .flatten();
+
+    html_response(&title, &user_profile.bio, image, &content)
+}
+
+fn post_html(post_id: &str) -> HttpResponse {
+    let Some((post, author)) = get_public_post(post_id) else {
+        return text_response(404, "Post not found");
+    };
+
+    let title = format!("Post by @{}", author.username);
+    let engagement = if post.hide_like_count {
+        format!("{} comments", post.comments_count)
+    } else {
+        format!("{} likes · {} comments", post.likes_count, post.comments_count)
+    };
+    let content = format!(
+        "<article>{}<p><a href=\"/u/{}\">@{}</a> {}</p><p>{}</p></article>",
+        media_preview_html(&post),
+        escape_html(&author.username),
+        escape_html(&author.username),
+        escape_html(&post.caption),
+        engagement,
+    );
+
+    // Labeled posts don't get a link preview image
+    let image = post
+        .content_warning
+        .is_none()
+        .then(|| post.media.first().map(|item| item.url.as_str()))
+        .flatten();
+
+    html_response(&title, &post.caption, image, &content)
+}
+
+fn media_preview_html(post: &Post) -> String {
+    let Some(item) = post.media.first() else {
+        return String::new();
+    };
+
+    if let Some(warning) = &post.content_warning {
+        return format!("<p>Content warning: {:?}</p>", warning);
+    }
+
+    let alt_text = escape_html(item.alt_text.as_deref().unwrap_or(""));
+    match item.media_type {
+        MediaType::Image => format!("<img src=\"{}\" alt=\"{}\">", escape_html(&item.url), alt_text),
+        MediaType::Video => format!("<video src=\"{}\" controls aria-label=\"{}\"></video>", escape_html(&item.url), alt_text),
+    }
+}
+
+// Open Graph tags let link unfurlers build previews
+fn html_response(title: &str, description: &str, image: Option<&str>, content: &str) -> HttpResponse {
+    let image_meta = image
+        .map(|url| format!("<meta property=\"og:image\" content=\"{}\">", escape_html(url)))
+        .unwrap_or_default();
+
+    let body = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <meta property=\"og:title\" content=\"{title}\">\
+         <meta property=\"og:description\" content=\"{description}\">{image_meta}</head>\
+         <body>{content}</body></html>",
+        title = escape_html(title),
+        description = escape_html(description),
+        image_meta = image_meta,
+        content = content,
+    );
+
+    HttpResponse {
+        status_code: 200,
+        headers: vec![
+            ("Content-Type".to_string(), "text/html; charset=utf-8".to_string()),
+            ("Cache-Control".to_string(), PAGE_CACHE_CONTROL.to_string()),
+        ],
+        body: body.into_bytes(),
+        streaming_strategy: None,
+    }
+}
+
+fn json_response(status_code: u16, body: serde_json::Value, cache_control: &str) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Cache-Control".to_string(), cache_control.to_string()),
+        ],
+        body: body.to_string().into_bytes(),
+        streaming_strategy: None,
+    }
+}
+
+fn text_response(status_code: u16, message: &str) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![
+            ("Content-Type".to_string(), "text/plain; charset=utf-8".to_string()),
+            ("Cache-Control".to_string(), ERROR_CACHE_CONTROL.to_string()),
+        ],
+        body: message.as_bytes().to_vec(),
+        streaming_strategy: None,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
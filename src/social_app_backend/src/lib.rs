@@ -2,12 +2,17 @@ use candid::Principal;
 use ic_cdk::{caller, query, update};
 use std::collections::HashMap;
 
+mod audio;
 mod content_management;
 mod discovery;
+mod http_gateway;
+mod live_streaming;
+mod media_storage;
 mod messaging;
 mod notifications;
 mod profile_management;
 mod safety_privacy;
+mod scheduling;
 mod shopping;
 mod social_features;
 mod state_handler;
@@ -47,7 +52,7 @@ impl Default for AppConfig {
 // Export main API functions
 #[query]
 fn get_user_profile(user_id: String) -> Result<UserProfile, String> {
-    profile_management::get_user_profile(user_id)
+    profile_management::get_user_profile(user_id).map(profile_management::prepare_profile_for_caller)
 }
 
 #[update]
@@ -57,7 +62,7 @@ fn create_user_profile(profile_data: CreateUserProfileRequest) -> Result<UserPro
 
 #[update]
 fn create_multiple_profiles(count: u32) -> Result<Vec<UserProfile>, String> {
-    user_management::create_multiple_profiles(count)
+    user_management::create_multiple_profiles(count).map(profile_management::prepare_profiles_for_caller)
 }
 
 #[update]
@@ -67,12 +72,12 @@ fn update_user_profile(profile_data: UpdateUserProfileRequest) -> Result<UserPro
 
 #[update]
 fn create_post(post_data: CreatePostRequest) -> Result<Post, String> {
-    content_management::create_post(post_data)
+    content_management::create_post(post_data).map(content_management::prepare_post_for_caller)
 }
 
 #[query]
 fn get_feed(limit: Option<u32>, offset: Option<u32>) -> Result<Vec<Post>, String> {
-    content_management::get_feed(limit.unwrap_or(20), offset.unwrap_or(0))
+    content_management::get_feed(limit.unwrap_or(20), offset.unwrap_or(0)).map(content_management::prepare_posts_for_caller)
 }
 
 #[update]
@@ -104,14 +109,28 @@ fn get_messages(conversation_id: String, limit: Option<u32>) -> Result<Vec<Messa
     messaging::get_messages(conversation_id, limit.unwrap_or(50))
 }
 
+#[update]
+fn reply_to_story(story_id: String, content: String) -> Result<Message, String> {
+    messaging::reply_to_story(story_id, content)
+}
+
+#[update]
+fn react_to_story(story_id: String, emoji: String) -> Result<Message, String> {
+    messaging::react_to_story(story_id, emoji)
+}
+
 #[query]
 fn search_content(query: String, search_type: SearchType) -> Result<SearchResults, String> {
-    discovery::search_content(query, search_type)
+    discovery::search_content(query, search_type).map(|mut results| {
+        results.users = profile_management::prepare_profiles_for_caller(results.users);
+        results.posts = content_management::prepare_posts_for_caller(results.posts);
+        results
+    })
 }
 
 #[query]
 fn get_explore_content(limit: Option<u32>) -> Result<Vec<Post>, String> {
-    discovery::get_explore_content(limit.unwrap_or(20))
+    discovery::get_explore_content(limit.unwrap_or(20)).map(content_management::prepare_posts_for_caller)
 }
 
 // User Management Functions
@@ -122,12 +141,12 @@ fn register_user(user_id: String) -> Result<(), String> {
 
 #[query]
 fn get_user_info(user_id: String) -> Result<UserProfile, String> {
-    user_management::get_user_info(&user_id)
+    user_management::get_user_info(&user_id).map(profile_management::prepare_profile_for_caller)
 }
 
 #[query]
 fn search_users(query: String, limit: u32) -> Vec<UserProfile> {
-    user_management::search_users(&query, limit)
+    profile_management::prepare_profiles_for_caller(user_management::search_users(&query, limit))
 }
 
 #[query]
@@ -209,7 +228,7 @@ fn get_online_status(user_id: String) -> bool {
 // Content Management Functions
 #[query]
 fn get_post(post_id: String) -> Result<Post, String> {
-    content_management::get_post(&post_id)
+    content_management::get_post(&post_id).map(content_management::prepare_post_for_caller)
 }
 
 #[update]
@@ -218,7 +237,87 @@ fn update_post(
     caption: Option<String>,
     hashtags: Option<Vec<String>>,
 ) -> Result<Post, String> {
-    content_management::update_post(post_id, caption, hashtags)
+    content_management::update_post(post_id, caption, hashtags).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn set_hide_like_count(post_id: String, hidden: bool) -> Result<Post, String> {
+    content_management::set_hide_like_count(post_id, hidden)
+}
+
+#[update]
+fn vote_on_poll(post_id: String, option_index: u32) -> Result<Post, String> {
+    content_management::vote_on_poll(post_id, option_index).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn set_post_content_warning(post_id: String, warning: Option<ContentWarning>) -> Result<Post, String> {
+    content_management::set_post_content_warning(post_id, warning).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn set_story_content_warning(story_id: String, warning: Option<ContentWarning>) -> Result<Story, String> {
+    content_management::set_story_content_warning(story_id, warning)
+}
+
+#[update]
+fn set_remixes_allowed(post_id: String, allowed: bool) -> Result<Post, String> {
+    content_management::set_remixes_allowed(post_id, allowed).map(content_management::prepare_post_for_caller)
+}
+
+#[query]
+fn get_post_remixes(post_id: String, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+    content_management::get_post_remixes(post_id, limit, offset).map(content_management::prepare_posts_for_caller)
+}
+
+#[query]
+fn get_collab_invitations() -> Result<Vec<Post>, String> {
+    content_management::get_collab_invitations().map(content_management::prepare_posts_for_caller)
+}
+
+#[update]
+fn accept_collab_invitation(post_id: String) -> Result<Post, String> {
+    content_management::accept_collab_invitation(post_id).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn decline_collab_invitation(post_id: String) -> Result<(), String> {
+    content_management::decline_collab_invitation(post_id)
+}
+
+#[update]
+fn remove_self_from_collab(post_id: String) -> Result<Post, String> {
+    content_management::remove_self_from_collab(post_id).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn save_draft(post_data: CreatePostRequest) -> Result<PostDraft, String> {
+    content_management::save_draft(post_data)
+}
+
+#[query]
+fn get_drafts() -> Result<Vec<PostDraft>, String> {
+    content_management::get_drafts()
+}
+
+#[update]
+fn update_draft(draft_id: String, post_data: CreatePostRequest) -> Result<PostDraft, String> {
+    content_management::update_draft(draft_id, post_data)
+}
+
+#[update]
+fn delete_draft(draft_id: String) -> Result<(), String> {
+    content_management::delete_draft(draft_id)
+}
+
+#[update]
+fn publish_draft(draft_id: String) -> Result<Post, String> {
+    content_management::publish_draft(draft_id).map(content_management::prepare_post_for_caller)
+}
+
+#[query]
+fn get_post_revisions(post_id: String) -> Result<Vec<PostRevision>, String> {
+    content_management::get_post_revisions(post_id)
 }
 
 #[update]
@@ -228,33 +327,153 @@ fn delete_post(post_id: String) -> Result<(), String> {
 
 #[query]
 fn get_user_posts(user_id: String, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
-    content_management::get_user_posts(&user_id, limit, offset)
+    content_management::get_user_posts(&user_id, limit, offset).map(content_management::prepare_posts_for_caller)
+}
+
+#[update]
+fn pin_post(post_id: String) -> Result<Post, String> {
+    content_management::pin_post(post_id).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn unpin_post(post_id: String) -> Result<Post, String> {
+    content_management::unpin_post(post_id).map(content_management::prepare_post_for_caller)
 }
 
 #[update]
 fn archive_post(post_id: String) -> Result<Post, String> {
-    content_management::archive_post(post_id)
+    content_management::archive_post(post_id).map(content_management::prepare_post_for_caller)
 }
 
 #[update]
 fn unarchive_post(post_id: String) -> Result<Post, String> {
-    content_management::unarchive_post(post_id)
+    content_management::unarchive_post(post_id).map(content_management::prepare_post_for_caller)
 }
 
 #[query]
 fn get_archived_posts(limit: u32, offset: u32) -> Result<Vec<Post>, String> {
-    content_management::get_archived_posts(limit, offset)
+    content_management::get_archived_posts(limit, offset).map(content_management::prepare_posts_for_caller)
 }
 
 #[update]
 fn create_story(
-    media_url: String,
+    media: MediaItem,
     story_type: StoryType,
     text_overlay: Option<String>,
     stickers: Vec<Sticker>,
     music_info: Option<MusicInfo>,
+    audience: Option<StoryAudience>,
+    content_warning: Option<ContentWarning>,
 ) -> Result<Story, String> {
-    content_management::create_story(media_url, story_type, text_overlay, stickers, music_info)
+    content_management::create_story(media, story_type, text_overlay, stickers, music_info, audience, content_warning)
+}
+
+#[update]
+fn hide_story_from(user_id: String) -> Result<(), String> {
+    content_management::hide_story_from(user_id)
+}
+
+#[update]
+fn unhide_story_from(user_id: String) -> Result<(), String> {
+    content_management::unhide_story_from(user_id)
+}
+
+#[query]
+fn get_story_hidden_from() -> Result<Vec<UserProfile>, String> {
+    content_management::get_story_hidden_from().map(profile_management::prepare_profiles_for_caller)
+}
+
+#[update]
+fn update_story_reply_setting(setting: StoryReplySetting) -> Result<StoryReplySetting, String> {
+    content_management::update_story_reply_setting(setting)
+}
+
+#[query]
+fn get_story_reply_setting() -> Result<StoryReplySetting, String> {
+    content_management::get_story_reply_setting()
+}
+
+#[query]
+fn get_audio_track(track_id: String) -> Result<AudioTrack, String> {
+    audio::get_audio_track(track_id)
+}
+
+#[query]
+fn get_audio_page(track_id: String, limit: u32, offset: u32) -> Result<AudioPage, String> {
+    audio::get_audio_page(track_id, limit, offset).map(|mut page| {
+        page.reels = content_management::prepare_posts_for_caller(page.reels);
+        page
+    })
+}
+
+#[query]
+fn get_trending_audio(limit: u32) -> Result<Vec<AudioTrack>, String> {
+    audio::get_trending_audio(limit)
+}
+
+#[query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    http_gateway::http_request(request)
+}
+
+#[query]
+fn http_request_streaming_callback(token: StreamingCallbackToken) -> StreamingCallbackHttpResponse {
+    http_gateway::http_request_streaming_callback(token)
+}
+
+#[update]
+fn start_media_upload(mime_type: String, total_size: u64) -> Result<MediaUpload, String> {
+    media_storage::start_media_upload(mime_type, total_size)
+}
+
+#[update]
+fn upload_media_chunk(upload_id: String, chunk_index: u32, data: Vec<u8>) -> Result<(), String> {
+    media_storage::upload_media_chunk(upload_id, chunk_index, data)
+}
+
+#[update]
+fn finish_media_upload(upload_id: String) -> Result<StoredMedia, String> {
+    media_storage::finish_media_upload(upload_id)
+}
+
+#[update]
+fn cancel_media_upload(upload_id: String) -> Result<(), String> {
+    media_storage::cancel_media_upload(upload_id)
+}
+
+#[query]
+fn get_media_info(hash: String) -> Result<StoredMedia, String> {
+    media_storage::get_media_info(hash)
+}
+
+#[query]
+fn get_media_chunk(hash: String, chunk_index: u32) -> Result<Vec<u8>, String> {
+    media_storage::get_media_chunk(hash, chunk_index)
+}
+
+#[query]
+fn get_media_storage_usage() -> Result<MediaStorageUsage, String> {
+    media_storage::get_media_storage_usage()
+}
+
+#[update]
+fn schedule_post(post_data: CreatePostRequest, publish_at: u64) -> Result<ScheduledItem, String> {
+    scheduling::schedule_post(post_data, publish_at)
+}
+
+#[update]
+fn schedule_story(story_data: CreateStoryRequest, publish_at: u64) -> Result<ScheduledItem, String> {
+    scheduling::schedule_story(story_data, publish_at)
+}
+
+#[query]
+fn get_scheduled_items() -> Result<Vec<ScheduledItem>, String> {
+    scheduling::get_scheduled_items()
+}
+
+#[update]
+fn cancel_scheduled_item(schedule_id: String) -> Result<(), String> {
+    scheduling::cancel_scheduled_item(schedule_id)
 }
 
 #[update]
@@ -267,14 +486,119 @@ fn get_user_stories(user_id: String) -> Result<Vec<Story>, String> {
     content_management::get_user_stories(&user_id)
 }
 
+#[update]
+fn vote_story_sticker(story_id: String, sticker_index: u32, option_index: u32) -> Result<StickerResponse, String> {
+    content_management::respond_to_story_sticker(story_id, sticker_index, StickerResponseValue::Vote(option_index))
+}
+
+#[update]
+fn answer_story_sticker(story_id: String, sticker_index: u32, answer: String) -> Result<StickerResponse, String> {
+    content_management::respond_to_story_sticker(story_id, sticker_index, StickerResponseValue::Answer(answer))
+}
+
+#[update]
+fn slide_story_sticker(story_id: String, sticker_index: u32, value: f32) -> Result<StickerResponse, String> {
+    content_management::respond_to_story_sticker(story_id, sticker_index, StickerResponseValue::Slide(value))
+}
+
+#[update]
+fn subscribe_story_countdown(story_id: String, sticker_index: u32) -> Result<StickerResponse, String> {
+    content_management::respond_to_story_sticker(story_id, sticker_index, StickerResponseValue::Subscribe)
+}
+
+#[query]
+fn get_story_sticker_results(story_id: String) -> Result<Vec<StickerResults>, String> {
+    content_management::get_story_sticker_results(story_id)
+}
+
+#[query]
+fn get_story_archive() -> Result<Vec<Story>, String> {
+    content_management::get_story_archive()
+}
+
+#[update]
+fn update_story_archive_settings(retention_days: u32) -> Result<StoryArchiveSettings, String> {
+    content_management::update_story_archive_settings(retention_days)
+}
+
+#[query]
+fn get_story_archive_settings() -> Result<StoryArchiveSettings, String> {
+    content_management::get_story_archive_settings()
+}
+
+#[update]
+fn create_highlight(title: String, story_ids: Vec<String>) -> Result<Highlight, String> {
+    content_management::create_highlight(title, story_ids)
+}
+
+#[update]
+fn rename_highlight(highlight_id: String, title: String) -> Result<Highlight, String> {
+    content_management::rename_highlight(highlight_id, title)
+}
+
+#[update]
+fn reorder_highlights(highlight_ids: Vec<String>) -> Result<Vec<Highlight>, String> {
+    content_management::reorder_highlights(highlight_ids)
+}
+
+#[update]
+fn delete_highlight(highlight_id: String) -> Result<(), String> {
+    content_management::delete_highlight(highlight_id)
+}
+
+#[update]
+fn add_stories_to_highlight(highlight_id: String, story_ids: Vec<String>) -> Result<Highlight, String> {
+    content_management::add_stories_to_highlight(highlight_id, story_ids)
+}
+
+#[update]
+fn remove_story_from_highlight(highlight_id: String, story_id: String) -> Result<Highlight, String> {
+    content_management::remove_story_from_highlight(highlight_id, story_id)
+}
+
+#[update]
+fn set_highlight_cover(highlight_id: String, story_id: String) -> Result<Highlight, String> {
+    content_management::set_highlight_cover(highlight_id, story_id)
+}
+
+#[query]
+fn get_user_highlights(user_id: String) -> Result<Vec<Highlight>, String> {
+    content_management::get_user_highlights(&user_id)
+}
+
+#[query]
+fn get_highlight_stories(highlight_id: String) -> Result<Vec<Story>, String> {
+    content_management::get_highlight_stories(highlight_id)
+}
+
 #[query]
 fn get_trending_posts(limit: u32) -> Result<Vec<Post>, String> {
-    content_management::get_trending_posts(limit)
+    content_management::get_trending_posts(limit).map(content_management::prepare_posts_for_caller)
 }
 
 #[query]
 fn get_posts_by_hashtag(hashtag: String, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
-    content_management::get_posts_by_hashtag(&hashtag, limit, offset)
+    content_management::get_posts_by_hashtag(&hashtag, limit, offset).map(content_management::prepare_posts_for_caller)
+}
+
+#[query]
+fn get_tagged_posts(user_id: String, limit: u32, offset: u32) -> Result<Vec<Post>, String> {
+    content_management::get_tagged_posts(&user_id, limit, offset).map(content_management::prepare_posts_for_caller)
+}
+
+#[query]
+fn get_pending_tags() -> Result<Vec<Post>, String> {
+    content_management::get_pending_tags().map(content_management::prepare_posts_for_caller)
+}
+
+#[update]
+fn approve_tag(post_id: String) -> Result<Post, String> {
+    content_management::approve_tag(post_id).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn remove_tag(post_id: String) -> Result<(), String> {
+    content_management::remove_tag(post_id)
 }
 
 // Social Features Functions
@@ -289,8 +613,13 @@ fn reply_to_comment(comment_id: String, content: String) -> Result<Comment, Stri
 }
 
 #[query]
-fn get_post_comments(post_id: String, limit: u32, offset: u32) -> Result<Vec<Comment>, String> {
-    social_features::get_post_comments(post_id, limit, offset)
+fn get_post_comments(
+    post_id: String,
+    limit: u32,
+    offset: u32,
+    sort: Option<CommentSortMode>,
+) -> Result<Vec<Comment>, String> {
+    social_features::get_post_comments(post_id, limit, offset, sort)
 }
 
 #[query]
@@ -302,6 +631,51 @@ fn get_comment_replies(
     social_features::get_comment_replies(comment_id, limit, offset)
 }
 
+#[query]
+fn get_hidden_comments(post_id: String) -> Result<Vec<Comment>, String> {
+    social_features::get_hidden_comments(post_id)
+}
+
+#[update]
+fn approve_hidden_comment(comment_id: String) -> Result<Comment, String> {
+    social_features::approve_hidden_comment(comment_id)
+}
+
+#[update]
+fn delete_hidden_comment(comment_id: String) -> Result<(), String> {
+    social_features::delete_hidden_comment(comment_id)
+}
+
+#[update]
+fn like_comment(comment_id: String) -> Result<Comment, String> {
+    social_features::like_comment(comment_id)
+}
+
+#[update]
+fn unlike_comment(comment_id: String) -> Result<Comment, String> {
+    social_features::unlike_comment(comment_id)
+}
+
+#[update]
+fn edit_comment(comment_id: String, content: String) -> Result<Comment, String> {
+    social_features::edit_comment(comment_id, content)
+}
+
+#[update]
+fn delete_comment(comment_id: String) -> Result<(), String> {
+    social_features::delete_comment(comment_id)
+}
+
+#[update]
+fn pin_comment(comment_id: String) -> Result<Comment, String> {
+    social_features::pin_comment(comment_id)
+}
+
+#[update]
+fn unpin_comment(comment_id: String) -> Result<Comment, String> {
+    social_features::unpin_comment(comment_id)
+}
+
 #[update]
 fn unfollow_user(user_id: String) -> Result<(), String> {
     social_features::unfollow_user(user_id)
@@ -309,12 +683,12 @@ fn unfollow_user(user_id: String) -> Result<(), String> {
 
 #[query]
 fn get_followers(user_id: String, limit: u32, offset: u32) -> Result<Vec<UserProfile>, String> {
-    social_features::get_followers(user_id, limit, offset)
+    social_features::get_followers(user_id, limit, offset).map(profile_management::prepare_profiles_for_caller)
 }
 
 #[query]
 fn get_following(user_id: String, limit: u32, offset: u32) -> Result<Vec<UserProfile>, String> {
-    social_features::get_following(user_id, limit, offset)
+    social_features::get_following(user_id, limit, offset).map(profile_management::prepare_profiles_for_caller)
 }
 
 #[update]
@@ -329,7 +703,7 @@ fn unsave_post(post_id: String) -> Result<(), String> {
 
 #[query]
 fn get_saved_posts(limit: u32, offset: u32) -> Result<Vec<Post>, String> {
-    social_features::get_saved_posts(limit, offset)
+    social_features::get_saved_posts(limit, offset).map(content_management::prepare_posts_for_caller)
 }
 
 #[update]
@@ -354,7 +728,7 @@ fn remove_from_close_friends(friend_id: String) -> Result<(), String> {
 
 #[query]
 fn get_close_friends() -> Result<Vec<UserProfile>, String> {
-    social_features::get_close_friends()
+    social_features::get_close_friends().map(profile_management::prepare_profiles_for_caller)
 }
 
 // Discovery Functions
@@ -363,9 +737,37 @@ fn get_trending_hashtags(limit: u32) -> Result<Vec<Hashtag>, String> {
     discovery::get_trending_hashtags(limit)
 }
 
+#[query]
+fn get_hashtag_page(
+    hashtag: String,
+    tab: HashtagTab,
+    limit: u32,
+    offset: u32,
+) -> Result<HashtagPage, String> {
+    discovery::get_hashtag_page(hashtag, tab, limit, offset).map(|mut page| {
+        page.posts = content_management::prepare_posts_for_caller(page.posts);
+        page
+    })
+}
+
+#[update]
+fn follow_hashtag(hashtag: String) -> Result<Hashtag, String> {
+    discovery::follow_hashtag(hashtag)
+}
+
+#[update]
+fn unfollow_hashtag(hashtag: String) -> Result<(), String> {
+    discovery::unfollow_hashtag(hashtag)
+}
+
+#[query]
+fn get_followed_hashtags() -> Result<Vec<Hashtag>, String> {
+    discovery::get_followed_hashtags()
+}
+
 #[query]
 fn get_suggested_users(limit: u32) -> Result<Vec<UserProfile>, String> {
-    discovery::get_suggested_users(limit)
+    discovery::get_suggested_users(limit).map(profile_management::prepare_profiles_for_caller)
 }
 
 #[query]
@@ -374,7 +776,7 @@ fn get_posts_by_location(
     limit: u32,
     offset: u32,
 ) -> Result<Vec<Post>, String> {
-    discovery::get_posts_by_location(location, limit, offset)
+    discovery::get_posts_by_location(location, limit, offset).map(content_management::prepare_posts_for_caller)
 }
 
 #[query]
@@ -478,6 +880,81 @@ fn disable_vanish_mode(conversation_id: String) -> Result<(), String> {
     messaging::disable_vanish_mode(conversation_id)
 }
 
+// Live Streaming Functions
+#[update]
+fn start_live_stream(title: String) -> Result<LiveStream, String> {
+    live_streaming::start_live_stream(title)
+}
+
+#[update]
+fn end_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    live_streaming::end_live_stream(stream_id)
+}
+
+#[update]
+fn save_live_stream(stream_id: String, post_data: CreatePostRequest) -> Result<Post, String> {
+    live_streaming::save_live_stream(stream_id, post_data).map(content_management::prepare_post_for_caller)
+}
+
+#[update]
+fn join_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    live_streaming::join_live_stream(stream_id)
+}
+
+#[update]
+fn leave_live_stream(stream_id: String) -> Result<(), String> {
+    live_streaming::leave_live_stream(stream_id)
+}
+
+#[query]
+fn get_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    live_streaming::get_live_stream(stream_id)
+}
+
+#[query]
+fn get_following_live_streams() -> Result<Vec<LiveStream>, String> {
+    live_streaming::get_following_live_streams()
+}
+
+#[update]
+fn heartbeat_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    live_streaming::heartbeat_live_stream(stream_id)
+}
+
+#[update]
+fn post_live_comment(stream_id: String, content: String) -> Result<LiveComment, String> {
+    live_streaming::post_live_comment(stream_id, content)
+}
+
+#[query]
+fn get_live_comments(
+    stream_id: String,
+    after_sequence: Option<u64>,
+    limit: u32,
+) -> Result<Vec<LiveComment>, String> {
+    live_streaming::get_live_comments(stream_id, after_sequence, limit)
+}
+
+#[update]
+fn add_live_moderator(stream_id: String, user_id: String) -> Result<LiveStream, String> {
+    live_streaming::add_live_moderator(stream_id, user_id)
+}
+
+#[update]
+fn remove_live_moderator(stream_id: String, user_id: String) -> Result<LiveStream, String> {
+    live_streaming::remove_live_moderator(stream_id, user_id)
+}
+
+#[update]
+fn remove_live_comment(stream_id: String, comment_id: String) -> Result<(), String> {
+    live_streaming::remove_live_comment(stream_id, comment_id)
+}
+
+#[update]
+fn remove_live_viewer(stream_id: String, user_id: String) -> Result<LiveStream, String> {
+    live_streaming::remove_live_viewer(stream_id, user_id)
+}
+
 // Notification Functions
 #[update]
 fn send_notification(
@@ -593,12 +1070,19 @@ fn get_profile_analytics(user_id: String) -> Result<Analytics, String> {
 
 #[update]
 fn verify_account(user_id: String) -> Result<UserProfile, String> {
-    profile_management::verify_account(&user_id)
+    profile_management::verify_account(&user_id).map(profile_management::prepare_profile_for_caller)
+}
+
+#[update]
+fn update_business_profile(
+    profile_data: UpdateBusinessProfileRequest,
+) -> Result<UserProfile, String> {
+    profile_management::update_business_profile(profile_data)
 }
 
 #[query]
 fn get_public_profile_info(user_id: String) -> Result<UserProfile, String> {
-    profile_management::get_public_profile_info(&user_id)
+    profile_management::get_public_profile_info(&user_id).map(profile_management::prepare_profile_for_caller)
 }
 
 // Safety & Privacy Functions
@@ -652,6 +1136,26 @@ fn get_keyword_filters() -> Result<Vec<String>, String> {
     safety_privacy::get_keyword_filters()
 }
 
+#[update]
+fn update_tag_settings(allow_tags_from: TagPermission, manually_approve_tags: bool) -> Result<TagSettings, String> {
+    safety_privacy::update_tag_settings(allow_tags_from, manually_approve_tags)
+}
+
+#[query]
+fn get_tag_settings() -> Result<TagSettings, String> {
+    safety_privacy::get_tag_settings()
+}
+
+#[update]
+fn update_sensitive_content_level(level: SensitiveContentLevel) -> Result<SensitiveContentLevel, String> {
+    safety_privacy::update_sensitive_content_level(level)
+}
+
+#[query]
+fn get_sensitive_content_level() -> Result<SensitiveContentLevel, String> {
+    safety_privacy::get_sensitive_content_level()
+}
+
 #[update]
 fn enable_two_factor_auth() -> Result<(), String> {
     safety_privacy::enable_two_factor_auth()
@@ -775,6 +1279,10 @@ fn init() {
         APP_CONFIG = Some(AppConfig::default());
     }
     state_handler::init_state();
+    scheduling::start_publish_timer();
+    media_storage::start_gc_timer();
+    content_management::start_story_sweep_timer();
+    content_management::start_poll_close_timer();
 }
 
 // Pre-upgrade hook
@@ -787,6 +1295,11 @@ fn pre_upgrade() {
 #[ic_cdk::post_upgrade]
 fn post_upgrade() {
     state_handler::restore_state();
+    state_handler::rebuild_media_references();
+    scheduling::start_publish_timer();
+    media_storage::start_gc_timer();
+    content_management::start_story_sweep_timer();
+    content_management::start_poll_close_timer();
 }
 
 // Helper function to get current user with environment-aware authentication
@@ -821,6 +1334,19 @@ pub fn get_current_user() -> Result<String, String> {
     }
 }
 
+// Admin principals double as content moderators
+pub fn is_admin(user_id: &str) -> bool {
+    let Ok(principal) = Principal::from_text(user_id) else {
+        return false;
+    };
+
+    let config = unsafe { &*std::ptr::addr_of!(APP_CONFIG) };
+    config
+        .as_ref()
+        .map(|config| config.admin_principals.contains(&principal))
+        .unwrap_or(false)
+}
+
 // Environment management functions
 #[update]
 pub fn reset_to_development() -> Result<(), String> {
@@ -897,7 +1423,7 @@ fn greet(name: String) -> String {
 
 #[query]
 pub fn get_all_profiles() -> Result<Vec<UserProfile>, String> {
-    profile_management::get_all_profiles()
+    profile_management::get_all_profiles().map(profile_management::prepare_profiles_for_caller)
 }
 
 // Export Candid interface
@@ -0,0 +1,403 @@
+use crate::content_management;
+use crate::notifications;
+use crate::state_handler;
+use crate::types::*;
+use crate::user_management;
+
+const MAX_LIVE_TITLE_LENGTH: usize = 100;
+const MAX_LIVE_COMMENT_LENGTH: usize = 300;
+// Older comments are dropped once a stream holds this many
+const MAX_STORED_LIVE_COMMENTS: usize = 500;
+const MAX_LIVE_MODERATORS: usize = 10;
+// Clients send a heartbeat about every 20 seconds; viewers who miss this
+// long are no longer counted
+const LIVE_VIEWER_TIMEOUT: u64 = 60 * 1_000_000_000; // 60 seconds in nanoseconds
+
+pub fn start_live_stream(title: String) -> Result<LiveStream, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
+
+    if title.trim().is_empty() {
+        return Err("Live title cannot be empty".to_string());
+    }
+
+    if title.chars().count() > MAX_LIVE_TITLE_LENGTH {
+        return Err(format!(
+            "Live title must be {} characters or less",
+            MAX_LIVE_TITLE_LENGTH
+        ));
+    }
+
+    if state_handler::get_active_live_streams()
+        .iter()
+        .any(|stream| stream.user_id == user_id)
+    {
+        return Err("You already have an active live stream".to_string());
+    }
+
+    let stream = LiveStream {
+        stream_id: state_handler::generate_id(),
+        user_id: user_id.clone(),
+        title: title.trim().to_string(),
+        viewers_count: 0,
+        peak_viewers_count: 0,
+        viewers: Vec::new(),
+        moderators: Vec::new(),
+        removed_viewers: Vec::new(),
+        comments: Vec::new(),
+        next_comment_sequence: 1,
+        is_active: true,
+        started_at: state_handler::get_current_timestamp(),
+        ended_at: None,
+        saved_post_id: None,
+    };
+
+    state_handler::insert_live_stream(stream.clone());
+
+    for follower_id in state_handler::get_followers(&user_id) {
+        let _ = notifications::notify_live(follower_id, user_id.clone());
+    }
+
+    Ok(stream)
+}
+
+pub fn end_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    let user_id = user_management::authenticate_user()?;
+    let mut stream = get_hosted_stream(&stream_id, &user_id)?;
+
+    if !stream.is_active {
+        return Err("Live stream has already ended".to_string());
+    }
+
+    stream.is_active = false;
+    stream.ended_at = Some(state_handler::get_current_timestamp());
+    stream.viewers.clear();
+    stream.viewers_count = 0;
+
+    state_handler::insert_live_stream(stream.clone());
+    Ok(stream)
+}
+
+// Publishes the uploaded recording of an ended stream as a Live post
+pub fn save_live_stream(stream_id: String, post_data: CreatePostRequest) -> Result<Post, String> {
+    let user_id = user_management::authenticate_user()?;
+    let mut stream = get_hosted_stream(&stream_id, &user_id)?;
+
+    if stream.is_active {
+        return Err("End the live stream before saving it".to_string());
+    }
+
+    if stream.saved_post_id.is_some() {
+        return Err("Live stream has already been saved".to_string());
+    }
+
+    if !matches!(post_data.content_type, ContentType::Live) {
+        return Err("Saved live streams must use the Live content type".to_string());
+    }
+
+    let post = content_management::create_post_for_user(&user_id, post_data)?;
+
+    stream.saved_post_id = Some(post.post_id.clone());
+    state_handler::insert_live_stream(stream);
+
+    Ok(post)
+}
+
+pub fn join_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    let user_id = user_management::authenticate_user()?;
+    let stream = get_active_stream(&stream_id)?;
+
+    if stream.user_id == user_id {
+        return Err("You are hosting this live stream".to_string());
+    }
+
+    mark_viewer_present(stream, user_id)
+}
+
+// Keeps the caller counted as watching, adding them back if they were
+// dropped for missing heartbeats
+pub fn heartbeat_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    let user_id = user_management::authenticate_user()?;
+    let stream = get_active_stream(&stream_id)?;
+
+    if stream.user_id == user_id {
+        return Ok(stream);
+    }
+
+    mark_viewer_present(stream, user_id)
+}
+
+fn mark_viewer_present(mut stream: LiveStream, user_id: String) -> Result<LiveStream, String> {
+    if stream.removed_viewers.contains(&user_id)
+        || !content_management::can_view_profile_content(&stream.user_id, Some(&user_id))
+    {
+        return Err("Access denied".to_string());
+    }
+
+    let current_time = state_handler::get_current_timestamp();
+    match stream.viewers.iter_mut().find(|viewer| viewer.user_id == user_id) {
+        Some(viewer) => viewer.last_seen = current_time,
+        None => stream.viewers.push(LiveViewer {
+            user_id: user_id.clone(),
+            last_seen: current_time,
+        }),
+    }
+    drop_stale_viewers(&mut stream, current_time);
+    stream.peak_viewers_count = stream.peak_viewers_count.max(stream.viewers_count);
+
+    state_handler::insert_live_stream(stream.clone());
+    Ok(prepare_stream_for_viewer(stream, Some(&user_id)))
+}
+
+pub fn leave_live_stream(stream_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    let mut stream = get_active_stream(&stream_id)?;
+
+    if !is_watching(&stream, &user_id) {
+        return Err("You are not watching this live stream".to_string());
+    }
+
+    stream.viewers.retain(|viewer| viewer.user_id != user_id);
+    drop_stale_viewers(&mut stream, state_handler::get_current_timestamp());
+
+    state_handler::insert_live_stream(stream);
+    Ok(())
+}
+
+pub fn get_live_stream(stream_id: String) -> Result<LiveStream, String> {
+    let current_user = user_management::authenticate_user().ok();
+    let mut stream = get_visible_stream(&stream_id, current_user.as_deref())?;
+
+    drop_stale_viewers(&mut stream, state_handler::get_current_timestamp());
+    Ok(prepare_stream_for_viewer(stream, current_user.as_deref()))
+}
+
+// Active streams hosted by people the caller follows, most watched first
+pub fn get_following_live_streams() -> Result<Vec<LiveStream>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let current_time = state_handler::get_current_timestamp();
+
+    let mut streams: Vec<LiveStream> = state_handler::get_active_live_streams()
+        .into_iter()
+        .filter(|stream| state_handler::is_following(&user_id, &stream.user_id))
+        .filter(|stream| !stream.removed_viewers.contains(&user_id))
+        .filter(|stream| content_management::can_view_profile_content(&stream.user_id, Some(&user_id)))
+        .map(|mut stream| {
+            drop_stale_viewers(&mut stream, current_time);
+            prepare_stream_for_viewer(stream, Some(&user_id))
+        })
+        .collect();
+
+    streams.sort_by_key(|stream| std::cmp::Reverse(stream.viewers_count));
+    Ok(streams)
+}
+
+pub fn post_live_comment(stream_id: String, content: String) -> Result<LiveComment, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let user_profile =
+        state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
+
+    let mut stream = get_active_stream(&stream_id)?;
+
+    // Viewers dropped for missing heartbeats get back in with heartbeat_live_stream
+    if stream.user_id != user_id && !is_watching(&stream, &user_id) {
+        return Err("Join the live stream before commenting".to_string());
+    }
+
+    if content.trim().is_empty() {
+        return Err("Comment cannot be empty".to_string());
+    }
+
+    if content.len() > MAX_LIVE_COMMENT_LENGTH {
+        return Err(format!(
+            "Comment must be {} characters or less",
+            MAX_LIVE_COMMENT_LENGTH
+        ));
+    }
+
+    let comment = LiveComment {
+        comment_id: state_handler::generate_id(),
+        user_id,
+        username: user_profile.username,
+        content,
+        sequence: stream.next_comment_sequence,
+        created_at: state_handler::get_current_timestamp(),
+    };
+
+    stream.next_comment_sequence += 1;
+    stream.comments.push(comment.clone());
+    if stream.comments.len() > MAX_STORED_LIVE_COMMENTS {
+        let excess = stream.comments.len() - MAX_STORED_LIVE_COMMENTS;
+        stream.comments.drain(..excess);
+    }
+
+    state_handler::insert_live_stream(stream);
+    Ok(comment)
+}
+
+// Comments posted after the given cursor (a LiveComment.sequence), oldest first
+pub fn get_live_comments(
+    stream_id: String,
+    after_sequence: Option<u64>,
+    limit: u32,
+) -> Result<Vec<LiveComment>, String> {
+    let current_user = user_management::authenticate_user().ok();
+    let stream = get_visible_stream(&stream_id, current_user.as_deref())?;
+
+    let after_sequence = after_sequence.unwrap_or(0);
+
+    Ok(stream
+        .comments
+        .into_iter()
+        .filter(|comment| comment.sequence > after_sequence)
+        .take(limit as usize)
+        .collect())
+}
+
+pub fn add_live_moderator(stream_id: String, moderator_id: String) -> Result<LiveStream, String> {
+    let user_id = user_management::authenticate_user()?;
+    let mut stream = get_hosted_stream(&stream_id, &user_id)?;
+
+    if moderator_id == user_id {
+        return Err("Hosts already moderate their own stream".to_string());
+    }
+
+    if !state_handler::user_exists(&moderator_id) {
+        return Err("User not found".to_string());
+    }
+
+    if stream.moderators.contains(&moderator_id) {
+        return Err("User is already a moderator".to_string());
+    }
+
+    if stream.moderators.len() >= MAX_LIVE_MODERATORS {
+        return Err(format!("A live stream can have up to {} moderators", MAX_LIVE_MODERATORS));
+    }
+
+    stream.moderators.push(moderator_id);
+    state_handler::insert_live_stream(stream.clone());
+    Ok(stream)
+}
+
+pub fn remove_live_moderator(stream_id: String, moderator_id: String) -> Result<LiveStream, String> {
+    let user_id = user_management::authenticate_user()?;
+    let mut stream = get_hosted_stream(&stream_id, &user_id)?;
+
+    if !stream.moderators.contains(&moderator_id) {
+        return Err("User is not a moderator".to_string());
+    }
+
+    stream.moderators.retain(|id| id != &moderator_id);
+    state_handler::insert_live_stream(stream.clone());
+    Ok(stream)
+}
+
+pub fn remove_live_comment(stream_id: String, comment_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    let mut stream = get_moderated_stream(&stream_id, &user_id)?;
+
+    let initial_len = stream.comments.len();
+    stream.comments.retain(|comment| comment.comment_id != comment_id);
+
+    if stream.comments.len() == initial_len {
+        return Err("Comment not found".to_string());
+    }
+
+    state_handler::insert_live_stream(stream);
+    Ok(())
+}
+
+// Removed viewers are kicked out and can't rejoin this stream
+pub fn remove_live_viewer(stream_id: String, viewer_id: String) -> Result<LiveStream, String> {
+    let user_id = user_management::authenticate_user()?;
+    let mut stream = get_moderated_stream(&stream_id, &user_id)?;
+
+    if viewer_id == stream.user_id || stream.moderators.contains(&viewer_id) {
+        return Err("Hosts and moderators cannot be removed".to_string());
+    }
+
+    if stream.removed_viewers.contains(&viewer_id) {
+        return Err("Viewer has already been removed".to_string());
+    }
+
+    stream.viewers.retain(|viewer| viewer.user_id != viewer_id);
+    stream.viewers_count = stream.viewers.len() as u64;
+    stream.removed_viewers.push(viewer_id);
+
+    state_handler::insert_live_stream(stream.clone());
+    Ok(stream)
+}
+
+// Removed viewers lose access to the stream entirely
+fn get_visible_stream(stream_id: &str, viewer_id: Option<&str>) -> Result<LiveStream, String> {
+    let stream = state_handler::get_live_stream(stream_id)
+        .ok_or_else(|| "Live stream not found".to_string())?;
+
+    if viewer_id.is_some_and(|user_id| stream.removed_viewers.iter().any(|id| id == user_id))
+        || !content_management::can_view_profile_content(&stream.user_id, viewer_id)
+    {
+        return Err("Access denied".to_string());
+    }
+
+    Ok(stream)
+}
+
+// Viewer, moderator and removed lists are only for the host and moderators
+fn prepare_stream_for_viewer(mut stream: LiveStream, viewer_id: Option<&str>) -> LiveStream {
+    let is_staff = viewer_id
+        .is_some_and(|user_id| stream.user_id == user_id || stream.moderators.iter().any(|id| id == user_id));
+
+    if !is_staff {
+        stream.viewers.clear();
+        stream.moderators.clear();
+        stream.removed_viewers.clear();
+    }
+
+    stream
+}
+
+fn drop_stale_viewers(stream: &mut LiveStream, current_time: u64) {
+    stream
+        .viewers
+        .retain(|viewer| current_time.saturating_sub(viewer.last_seen) <= LIVE_VIEWER_TIMEOUT);
+    stream.viewers_count = stream.viewers.len() as u64;
+}
+
+fn is_watching(stream: &LiveStream, user_id: &str) -> bool {
+    stream.viewers.iter().any(|viewer| viewer.user_id == user_id)
+}
+
+fn get_active_stream(stream_id: &str) -> Result<LiveStream, String> {
+    let stream = state_handler::get_live_stream(stream_id)
+        .ok_or_else(|| "Live stream not found".to_string())?;
+
+    if !stream.is_active {
+        return Err("Live stream has ended".to_string());
+    }
+
+    Ok(stream)
+}
+
+fn get_hosted_stream(stream_id: &str, user_id: &str) -> Result<LiveStream, String> {
+    let stream = state_handler::get_live_stream(stream_id)
+        .ok_or_else(|| "Live stream not found".to_string())?;
+
+    if stream.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+
+    Ok(stream)
+}
+
+fn get_moderated_stream(stream_id: &str, user_id: &str) -> Result<LiveStream, String> {
+    let stream = get_active_stream(stream_id)?;
+
+    if stream.user_id != user_id && !stream.moderators.iter().any(|id| id == user_id) {
+        return Err("Only the host and moderators can do this".to_string());
+    }
+
+    Ok(stream)
+}
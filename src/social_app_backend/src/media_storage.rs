@@ -0,0 +1,277 @@
+use crate::content_management;
+use crate::state_handler;
+use crate::types::*;
+use crate::user_management;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+// Stays well below the ingress message limit so each chunk fits in one call
+pub const CHUNK_SIZE: u64 = 1024 * 1024; // 1 MiB
+const MAX_MEDIA_SIZE: u64 = 100 * 1024 * 1024; // 100 MiB
+const USER_STORAGE_QUOTA: u64 = 2 * 1024 * 1024 * 1024; // 2 GiB
+const MAX_PENDING_UPLOADS_PER_USER: usize = 5;
+// Caps staged upload data across all users
+const MAX_PENDING_UPLOAD_BYTES: u64 = 4 * 1024 * 1024 * 1024; // 4 GiB
+const GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// Unreferenced blobs and unfinished uploads are kept this long so clients
+// have time to attach freshly uploaded media to a post
+const UNREFERENCED_GRACE_PERIOD: u64 = 24 * 60 * 60 * 1_000_000_000; // 24 hours in nanoseconds
+
+pub fn start_gc_timer() {
+    ic_cdk_timers::set_timer_interval(GC_INTERVAL, collect_garbage);
+}
+
+pub fn start_media_upload(mime_type: String, total_size: u64) -> Result<MediaUpload, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
+
+    let mime_type = mime_type.trim().to_lowercase();
+    if !["image/", "video/", "audio/"]
+        .iter()
+        .any(|prefix| mime_type.starts_with(prefix))
+    {
+        return Err("Only image, video and audio uploads are supported".to_string());
+    }
+
+    if total_size == 0 {
+        return Err("Upload cannot be empty".to_string());
+    }
+
+    if total_size > MAX_MEDIA_SIZE {
+        return Err(format!("Media must be {} bytes or less", MAX_MEDIA_SIZE));
+    }
+
+    let pending_uploads = state_handler::get_user_media_uploads(&user_id);
+    if pending_uploads.len() >= MAX_PENDING_UPLOADS_PER_USER {
+        return Err(format!(
+            "Maximum of {} uploads in progress allowed",
+            MAX_PENDING_UPLOADS_PER_USER
+        ));
+    }
+
+    // Uploads in progress count against the quota too
+    let pending_bytes: u64 = pending_uploads.iter().map(|upload| upload.total_size).sum();
+    if state_handler::get_media_usage(&user_id) + pending_bytes + total_size > USER_STORAGE_QUOTA {
+        return Err("Media storage quota exceeded".to_string());
+    }
+
+    let all_pending_bytes: u64 = state_handler::get_all_media_uploads()
+        .iter()
+        .map(|upload| upload.total_size)
+        .sum();
+    if all_pending_bytes + total_size > MAX_PENDING_UPLOAD_BYTES {
+        return Err("Too many uploads in progress, please try again later".to_string());
+    }
+
+    let upload = MediaUpload {
+        upload_id: state_handler::generate_id(),
+        user_id,
+        mime_type,
+        total_size,
+        chunk_size: CHUNK_SIZE,
+        chunk_count: total_size.div_ceil(CHUNK_SIZE) as u32,
+        created_at: state_handler::get_current_timestamp(),
+    };
+
+    state_handler::insert_media_upload(upload.clone());
+    Ok(upload)
+}
+
+pub fn upload_media_chunk(upload_id: String, chunk_index: u32, data: Vec<u8>) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    let upload = get_owned_upload(&upload_id, &user_id)?;
+
+    if chunk_index >= upload.chunk_count {
+        return Err("Chunk index out of range".to_string());
+    }
+
+    // Every chunk but the last must be exactly CHUNK_SIZE
+    let expected_size = if chunk_index + 1 == upload.chunk_count {
+        upload.total_size - CHUNK_SIZE * (upload.chunk_count as u64 - 1)
+    } else {
+        CHUNK_SIZE
+    };
+
+    if data.len() as u64 != expected_size {
+        return Err(format!("Chunk {} must be {} bytes", chunk_index, expected_size));
+    }
+
+    state_handler::set_media_upload_chunk(&upload_id, chunk_index, data);
+    Ok(())
+}
+
+pub fn finish_media_upload(upload_id: String) -> Result<StoredMedia, String> {
+    let user_id = user_management::authenticate_user()?;
+    let upload = get_owned_upload(&upload_id, &user_id)?;
+
+    if !state_handler::has_all_media_upload_chunks(&upload) {
+        return Err("Upload is incomplete".to_string());
+    }
+
+    let mut hasher = Sha256::new();
+    for chunk_index in 0..upload.chunk_count {
+        let chunk = state_handler::get_media_upload_chunk(&upload_id, chunk_index)
+            .ok_or_else(|| "Upload is incomplete".to_string())?;
+        hasher.update(chunk);
+    }
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    let used_bytes = state_handler::get_media_usage(&user_id);
+
+    // Identical content is stored once; the uploader becomes a co-owner
+    if let Some(mut existing) = state_handler::get_media(&hash) {
+        if !existing.owners.contains(&user_id) {
+            if used_bytes + existing.size > USER_STORAGE_QUOTA {
+                return Err("Media storage quota exceeded".to_string());
+            }
+            state_handler::set_media_usage(&user_id, used_bytes + existing.size);
+            existing.owners.push(user_id);
+            state_handler::update_media(existing.clone());
+        }
+        state_handler::remove_media_upload(&upload_id);
+        return Ok(existing);
+    }
+
+    if used_bytes + upload.total_size > USER_STORAGE_QUOTA {
+        return Err("Media storage quota exceeded".to_string());
+    }
+
+    let media = StoredMedia {
+        hash: hash.clone(),
+        url: state_handler::media_url(&hash),
+        mime_type: upload.mime_type.clone(),
+        size: upload.total_size,
+        chunk_count: upload.chunk_count,
+        ref_count: 0,
+        owners: vec![user_id.clone()],
+        created_at: state_handler::get_current_timestamp(),
+    };
+
+    state_handler::promote_media_upload(&upload, media.clone());
+    state_handler::set_media_usage(&user_id, used_bytes + upload.total_size);
+
+    Ok(media)
+}
+
+pub fn cancel_media_upload(upload_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    get_owned_upload(&upload_id, &user_id)?;
+
+    state_handler::remove_media_upload(&upload_id);
+    Ok(())
+}
+
+pub fn get_media_info(hash: String) -> Result<StoredMedia, String> {
+    get_accessible_media(&hash)
+}
+
+pub fn get_media_chunk(hash: String, chunk_index: u32) -> Result<Vec<u8>, String> {
+    get_accessible_media(&hash)?;
+    state_handler::get_media_chunk(&hash, chunk_index).ok_or_else(|| "Media chunk not found".to_string())
+}
+
+// Unknown and inaccessible blobs look the same so hashes can't be probed
+fn get_accessible_media(hash: &str) -> Result<StoredMedia, String> {
+    let current_user = user_management::authenticate_user().ok();
+
+    state_handler::get_media(hash)
+        .filter(|media| can_access_media(media, current_user.as_deref()))
+        .ok_or_else(|| "Media not found".to_string())
+}
+
+// Owners can always read their blobs; anyone else needs to be able to see
+// some content that references it
+fn can_access_media(media: &StoredMedia, viewer_id: Option<&str>) -> bool {
+    if viewer_id.is_some_and(|user_id| media.owners.iter().any(|owner| owner == user_id)) {
+        return true;
+    }
+
+    // Profile pictures are shown even on private profiles
+    if !state_handler::get_users_with_profile_picture(&media.url).is_empty() {
+        return true;
+    }
+
+    let visible_post = state_handler::get_posts_with_media(&media.url).iter().any(|post| {
+        let is_author = viewer_id.is_some_and(|user_id| post.is_author(user_id));
+        content_management::can_view_post(post, viewer_id)
+            && (is_author
+                || (!post.is_archived && content_management::can_view_profile_content(&post.user_id, viewer_id)))
+    });
+    if visible_post {
+        return true;
+    }
+
+    // Expired stories stay visible to their owner, and to profile visitors when highlighted
+    let current_time = state_handler::get_current_timestamp();
+    let visible_story = state_handler::get_stories_with_media(&media.url).iter().any(|story| {
+        if viewer_id == Some(story.user_id.as_str()) {
+            return true;
+        }
+        if story.expires_at <= current_time && !story.is_highlight {
+            return false;
+        }
+        content_management::can_view_story(story, viewer_id)
+    });
+    if visible_story {
+        return true;
+    }
+
+    viewer_id.is_some_and(|user_id| state_handler::is_media_in_user_conversations(user_id, &media.url))
+}
+
+pub fn get_media_storage_usage() -> Result<MediaStorageUsage, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    Ok(MediaStorageUsage {
+        used_bytes: state_handler::get_media_usage(&user_id),
+        quota_bytes: USER_STORAGE_QUOTA,
+    })
+}
+
+// Canister-hosted media must exist and belong to the user before their
+// content can reference it
+pub fn validate_media_url(url: &str, user_id: &str) -> Result<(), String> {
+    if let Some(hash) = state_handler::media_hash_from_url(url) {
+        let media = state_handler::get_media(hash)
+            .ok_or_else(|| "Referenced media has not been uploaded".to_string())?;
+
+        if !media.owners.iter().any(|owner| owner == user_id) {
+            return Err("Referenced media was not uploaded by you".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn get_owned_upload(upload_id: &str, user_id: &str) -> Result<MediaUpload, String> {
+    let upload = state_handler::get_media_upload(upload_id)
+        .ok_or_else(|| "Upload not found".to_string())?;
+
+    if upload.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+
+    Ok(upload)
+}
+
+fn collect_garbage() {
+    let current_time = state_handler::get_current_timestamp();
+    let cutoff = current_time.saturating_sub(UNREFERENCED_GRACE_PERIOD);
+
+    for upload in state_handler::get_all_media_uploads() {
+        if upload.created_at < cutoff {
+            state_handler::remove_media_upload(&upload.upload_id);
+        }
+    }
+
+    for media in state_handler::get_all_media() {
+        if media.ref_count == 0 && media.created_at < cutoff {
+            state_handler::delete_media(&media.hash);
+        }
+    }
+}
@@ -1,4 +1,6 @@
 use crate::types::*;
+use crate::content_management;
+use crate::media_storage;
 use crate::state_handler;
 use crate::user_management;
 
@@ -47,6 +49,7 @@ pub fn send_message(recipient_id: String, content: String, message_type: Message
         is_vanish_mode: false,
         created_at: current_time,
         expires_at: None,
+        story: None,
     };
     
     state_handler::insert_message(conversation_id, message.clone());
@@ -77,6 +80,8 @@ pub fn send_media_message(recipient_id: String, media_url: String, message_type:
         return Err("Invalid message type for media".to_string());
     }
     
+    media_storage::validate_media_url(&media_url, &sender_id)?;
+    
     // Find or create conversation
     let conversation_id = get_or_create_conversation(&sender_id, &recipient_id)?;
     
@@ -90,15 +95,17 @@ pub fn send_media_message(recipient_id: String, media_url: String, message_type:
         recipient_id: recipient_id.clone(),
         content: caption.unwrap_or_default(),
         message_type,
-        media_url: Some(media_url),
+        media_url: Some(media_url.clone()),
         reply_to: None,
         reactions: std::collections::HashMap::new(),
         is_read: false,
         is_vanish_mode: false,
         created_at: current_time,
         expires_at: None,
+        story: None,
     };
     
+    state_handler::retain_media_url(&media_url);
     state_handler::insert_message(conversation_id, message.clone());
     
     Ok(message)
@@ -127,6 +134,7 @@ pub fn reply_to_message(original_message_id: String, content: String, message_ty
         is_vanish_mode: false,
         created_at: current_time,
         expires_at: None,
+        story: None,
     };
     
     // TODO: Insert message properly
@@ -134,6 +142,77 @@ pub fn reply_to_message(original_message_id: String, content: String, message_ty
     Ok(message)
 }
 
+const MAX_STORY_REACTION_LENGTH: usize = 16;
+
+pub fn reply_to_story(story_id: String, content: String) -> Result<Message, String> {
+    if content.trim().is_empty() {
+        return Err("Reply cannot be empty".to_string());
+    }
+    
+    if content.len() > 1000 {
+        return Err("Message must be 1000 characters or less".to_string());
+    }
+    
+    send_story_message(story_id, content, false)
+}
+
+pub fn react_to_story(story_id: String, emoji: String) -> Result<Message, String> {
+    if emoji.trim().is_empty() || emoji.len() > MAX_STORY_REACTION_LENGTH {
+        return Err("Invalid reaction".to_string());
+    }
+    
+    send_story_message(story_id, emoji, true)
+}
+
+// Story replies and reactions land in the direct conversation with the owner
+fn send_story_message(story_id: String, content: String, is_reaction: bool) -> Result<Message, String> {
+    let sender_id = user_management::authenticate_user()?;
+    
+    let story = state_handler::get_story(&story_id)
+        .ok_or_else(|| "Story not found".to_string())?;
+    
+    content_management::check_story_reply_allowed(&story, &sender_id)?;
+    
+    let conversation_id = state_handler::get_or_create_conversation(&sender_id, &story.user_id)?;
+    
+    let message = Message {
+        message_id: state_handler::generate_id(),
+        conversation_id: conversation_id.clone(),
+        sender_id,
+        recipient_id: story.user_id.clone(),
+        content,
+        message_type: MessageType::Story,
+        media_url: None,
+        reply_to: None,
+        reactions: std::collections::HashMap::new(),
+        is_read: false,
+        is_vanish_mode: false,
+        created_at: state_handler::get_current_timestamp(),
+        expires_at: None,
+        story: Some(StoryReference {
+            story_id,
+            story_owner_id: story.user_id,
+            preview_url: Some(story.media.url),
+            story_expires_at: story.expires_at,
+            is_reaction,
+        }),
+    };
+    
+    state_handler::insert_message(conversation_id, message.clone());
+    
+    Ok(message)
+}
+
+// The story preview is only shown while the story is still live
+fn prepare_message_for_reader(mut message: Message) -> Message {
+    if let Some(story) = &mut message.story {
+        if story.story_expires_at <= state_handler::get_current_timestamp() {
+            story.preview_url = None;
+        }
+    }
+    message
+}
+
 pub fn get_messages(conversation_id: String, limit: u32) -> Result<Vec<Message>, String> {
     let user_id = user_management::authenticate_user()?;
     
@@ -146,7 +225,7 @@ pub fn get_messages(conversation_id: String, limit: u32) -> Result<Vec<Message>,
     }
     
     let messages = state_handler::get_conversation_messages(&conversation_id, limit);
-    Ok(messages)
+    Ok(messages.into_iter().map(prepare_message_for_reader).collect())
 }
 
 pub fn get_conversations(limit: u32, offset: u32) -> Result<Vec<Conversation>, String> {
@@ -164,6 +243,8 @@ pub fn get_conversations(limit: u32, offset: u32) -> Result<Vec<Conversation>, S
     
     for conversation in all_conversations {
         if conversation.participants.contains(&user_id) {
+            let mut conversation = conversation;
+            conversation.last_message = conversation.last_message.map(prepare_message_for_reader);
             user_conversations.push(conversation);
         }
     }
@@ -100,6 +100,7 @@ pub fn update_notification_settings(
             follows_enabled: true,
             mentions_enabled: true,
             tags_enabled: true,
+            remixes_enabled: true,
             messages_enabled: true,
             user_id: user_id.clone(),
             story_views_enabled: true,
@@ -116,6 +117,7 @@ pub fn update_notification_settings(
             NotificationType::Follow => settings.follows_enabled = enabled,
             NotificationType::Mention => settings.mentions_enabled = enabled,
             NotificationType::Tag => settings.tags_enabled = enabled,
+            NotificationType::Remix => settings.remixes_enabled = enabled,
             NotificationType::Message => settings.messages_enabled = enabled,
             NotificationType::Story => settings.story_views_enabled = enabled,
             NotificationType::Live => todo!(),
@@ -143,6 +145,7 @@ pub fn get_notification_settings() -> Result<NotificationSettings, String> {
                 follows_enabled: true,
                 mentions_enabled: true,
                 tags_enabled: true,
+                remixes_enabled: true,
                 messages_enabled: true,
                 user_id: user_id.clone(),
                 story_views_enabled: true,
@@ -178,6 +181,83 @@ pub fn notify_like(post_owner_id: String, liker_id: String, post_id: String) ->
     Ok(())
 }
 
+pub fn notify_comment_like(
+    comment_owner_id: String,
+    liker_id: String,
+    post_id: String,
+    comment_id: String,
+) -> Result<(), String> {
+    let liker_profile =
+        state_handler::get_user(&liker_id).ok_or_else(|| "User not found".to_string())?;
+
+    send_notification(
+        comment_owner_id,
+        NotificationType::Like,
+        "New Like".to_string(),
+        format!("{} liked your comment", liker_profile.username),
+        Some(liker_id),
+        Some(post_id),
+        Some(comment_id),
+    )?;
+
+    Ok(())
+}
+
+pub fn notify_poll_ended(author_id: String, post_id: String, poll: &PostPoll) -> Result<(), String> {
+    let top_votes = poll.options.iter().map(|option| option.votes_count).max().unwrap_or(0);
+    let leaders: Vec<&str> = poll
+        .options
+        .iter()
+        .filter(|option| option.votes_count == top_votes)
+        .map(|option| option.text.as_str())
+        .collect();
+
+    let outcome = if poll.total_votes == 0 {
+        "Your poll ended with no votes".to_string()
+    } else if leaders.len() > 1 {
+        format!(
+            "Your poll ended in a tie between \"{}\" with {} of {} votes each",
+            leaders.join("\", \""),
+            top_votes,
+            poll.total_votes
+        )
+    } else {
+        format!(
+            "Your poll ended: \"{}\" won with {} of {} votes",
+            leaders[0], top_votes, poll.total_votes
+        )
+    };
+
+    send_notification(
+        author_id,
+        NotificationType::System,
+        "Poll Ended".to_string(),
+        outcome,
+        None,
+        Some(post_id),
+        None,
+    )?;
+
+    Ok(())
+}
+
+pub fn notify_live(follower_id: String, host_id: String) -> Result<(), String> {
+    let host_profile =
+        state_handler::get_user(&host_id).ok_or_else(|| "User not found".to_string())?;
+
+    send_notification(
+        follower_id,
+        NotificationType::Live,
+        "Live Now".to_string(),
+        format!("{} started a live video", host_profile.username),
+        Some(host_id),
+        None,
+        None,
+    )?;
+
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub fn notify_comment(
     post_owner_id: String,
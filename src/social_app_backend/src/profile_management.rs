@@ -1,3 +1,5 @@
+use crate::content_management;
+use crate::media_storage;
 use crate::state_handler;
 use crate::types::*;
 use crate::user_management;
@@ -54,6 +56,10 @@ pub fn create_user_profile(profile_data: CreateUserProfileRequest) -> Result<Use
         }
     }
 
+    if let Some(profile_picture) = &profile_data.profile_picture {
+        media_storage::validate_media_url(profile_picture, &user_id)?;
+    }
+
     let current_time = state_handler::get_current_timestamp();
 
     let user_profile = UserProfile {
@@ -77,8 +83,13 @@ pub fn create_user_profile(profile_data: CreateUserProfileRequest) -> Result<Use
         location: profile_data.location,
         date_of_birth: profile_data.date_of_birth,
         gender: profile_data.gender,
+        business_profile: None,
     };
 
+    if let Some(profile_picture) = &user_profile.profile_picture {
+        state_handler::retain_media_url(profile_picture);
+    }
+
     state_handler::insert_user(user_id, user_profile.clone());
     Ok(user_profile)
 }
@@ -99,7 +110,7 @@ pub fn update_user_profile(profile_data: UpdateUserProfileRequest) -> Result<Use
     }
 
     if let Some(profile_picture) = profile_data.profile_picture {
-        user_profile.profile_picture = Some(profile_picture);
+        replace_profile_picture(&mut user_profile, Some(profile_picture))?;
     }
 
     if let Some(website) = profile_data.website {
@@ -137,11 +148,20 @@ pub fn delete_user_profile() -> Result<(), String> {
     let user_id = get_current_user()?;
 
     // Verify user exists before deletion
-    state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
+    let mut user_profile =
+        state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
+
+    // Release uploaded media held by the profile itself
+    replace_profile_picture(&mut user_profile, None)?;
+    state_handler::delete_user_media_usage(&user_id)?;
 
     // 1. Delete all user's posts
+    state_handler::delete_user_pinned_posts(&user_id)?;
+    content_management::leave_all_collab_posts(&user_id);
+    state_handler::delete_user_collab_invites(&user_id)?;
     let user_posts = state_handler::get_posts_by_user(&user_id);
     for post in user_posts {
+        content_management::release_collaborators(&post);
         // Delete post comments first
         state_handler::delete_post_comments(&post.post_id)?;
         // Delete the post itself
@@ -150,6 +170,8 @@ pub fn delete_user_profile() -> Result<(), String> {
 
     // 2. Delete all user's comments on other posts
     state_handler::delete_comments_by_user(&user_id)?;
+    state_handler::delete_user_tags(&user_id)?;
+    state_handler::delete_user_sensitive_content_level(&user_id)?;
 
     // 3. Remove all relationships (followers and following)
     // Remove user from other users' followers lists
@@ -167,8 +189,15 @@ pub fn delete_user_profile() -> Result<(), String> {
     // 4. Delete all messages (sent and received)
     state_handler::delete_user_messages(&user_id)?;
 
-    // Delete user's stories if they exist
+    // Delete user's stories and highlights if they exist
+    state_handler::delete_user_highlights(&user_id)?;
     state_handler::delete_user_stories(&user_id)?;
+    state_handler::delete_user_live_streams(&user_id)?;
+    state_handler::delete_user_followed_hashtags(&user_id)?;
+
+    // Drop anything still waiting to be published
+    state_handler::delete_user_scheduled_items(&user_id)?;
+    state_handler::delete_user_drafts(&user_id)?;
 
     // Delete user's saved posts
     state_handler::delete_user_saved_posts(&user_id)?;
@@ -220,7 +249,7 @@ pub fn update_profile_picture(image_url: String) -> Result<UserProfile, String>
     let mut user_profile =
         state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
 
-    user_profile.profile_picture = Some(image_url);
+    replace_profile_picture(&mut user_profile, Some(image_url))?;
     user_profile.updated_at = state_handler::get_current_timestamp();
 
     state_handler::update_user(&user_id, user_profile.clone())?;
@@ -233,13 +262,28 @@ pub fn remove_profile_picture() -> Result<UserProfile, String> {
     let mut user_profile =
         state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
 
-    user_profile.profile_picture = None;
+    replace_profile_picture(&mut user_profile, None)?;
     user_profile.updated_at = state_handler::get_current_timestamp();
 
     state_handler::update_user(&user_id, user_profile.clone())?;
     Ok(user_profile)
 }
 
+// Swaps the picture while keeping media reference counts in step
+fn replace_profile_picture(user_profile: &mut UserProfile, profile_picture: Option<String>) -> Result<(), String> {
+    if let Some(new_picture) = &profile_picture {
+        media_storage::validate_media_url(new_picture, &user_profile.user_id)?;
+        state_handler::retain_media_url(new_picture);
+    }
+
+    if let Some(old_picture) = &user_profile.profile_picture {
+        state_handler::release_media_url(old_picture);
+    }
+
+    user_profile.profile_picture = profile_picture;
+    Ok(())
+}
+
 pub fn update_bio(bio: String) -> Result<UserProfile, String> {
     let user_id = get_current_user()?;
 
@@ -327,15 +371,32 @@ pub fn get_profile_analytics(user_id: &str) -> Result<Analytics, String> {
         return Err("Access denied to analytics".to_string());
     }
 
-    // In a real implementation, you would calculate actual analytics
+    // Engagement totals come from the user's own posts. Hidden like counts
+    // are only revealed to the account owner.
+    let own_posts: Vec<Post> = state_handler::get_posts_by_user(user_id)
+        .into_iter()
+        .filter(|post| post.user_id == user_id)
+        .collect();
+
+    let likes_hidden = current_user != user_id
+        && (state_handler::get_privacy_settings(user_id).is_some_and(|settings| settings.hide_likes)
+            || own_posts.iter().any(|post| post.hide_like_count));
+
+    let likes = if likes_hidden {
+        0
+    } else {
+        own_posts.iter().map(|post| post.likes_count).sum()
+    };
+
+    // Reach, impressions and visits aren't tracked yet
     let analytics = Analytics {
         user_id: user_id.to_string(),
         post_id: None,
         story_id: None,
         views: 0,
-        likes: 0,
-        comments: 0,
-        shares: 0,
+        likes,
+        comments: own_posts.iter().map(|post| post.comments_count).sum(),
+        shares: own_posts.iter().map(|post| post.shares_count).sum(),
         saves: 0,
         reach: 0,
         impressions: 0,
@@ -391,6 +452,7 @@ pub fn get_public_profile_info(user_id: &str) -> Result<UserProfile, String> {
                     location: None,
                     date_of_birth: None,
                     gender: None,
+                    business_profile: None,
                 });
             }
         } else {
@@ -401,6 +463,149 @@ pub fn get_public_profile_info(user_id: &str) -> Result<UserProfile, String> {
     Ok(user_profile)
 }
 
+pub fn update_business_profile(
+    profile_data: UpdateBusinessProfileRequest,
+) -> Result<UserProfile, String> {
+    let user_id = get_current_user()?;
+
+    let mut user_profile =
+        state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
+
+    if !matches!(
+        user_profile.account_type,
+        AccountType::Business | AccountType::Creator
+    ) {
+        return Err("Only business and creator accounts have business profiles".to_string());
+    }
+
+    let current_time = state_handler::get_current_timestamp();
+    let mut business_profile = user_profile
+        .business_profile
+        .take()
+        .unwrap_or(BusinessProfile {
+            category: None,
+            contact_email: None,
+            contact_phone: None,
+            address: None,
+            action_buttons: Vec::new(),
+            show_contact_options: true,
+            updated_at: current_time,
+        });
+
+    if let Some(category) = profile_data.category {
+        business_profile.category = Some(category);
+    }
+
+    // An empty string removes the contact option
+    if let Some(email) = profile_data.contact_email {
+        if email.is_empty() {
+            business_profile.contact_email = None;
+        } else if !email.contains('@') || email.len() < 5 {
+            return Err("Invalid email address".to_string());
+        } else {
+            business_profile.contact_email = Some(email);
+        }
+    }
+
+    if let Some(phone) = profile_data.contact_phone {
+        if phone.is_empty() {
+            business_profile.contact_phone = None;
+        } else if phone.len() < 10 || phone.len() > 15 {
+            return Err("Phone number must be between 10-15 digits".to_string());
+        } else {
+            business_profile.contact_phone = Some(phone);
+        }
+    }
+
+    // An address with empty street, city and country removes it
+    if let Some(address) = profile_data.address {
+        if address.street.is_empty() && address.city.is_empty() && address.country.is_empty() {
+            business_profile.address = None;
+        } else if address.street.trim().is_empty()
+            || address.city.trim().is_empty()
+            || address.country.trim().is_empty()
+        {
+            return Err("Address must include street, city and country".to_string());
+        } else {
+            business_profile.address = Some(address);
+        }
+    }
+
+    if let Some(action_buttons) = profile_data.action_buttons {
+        for (index, button) in action_buttons.iter().enumerate() {
+            if !button.url.starts_with("http://") && !button.url.starts_with("https://") {
+                return Err(
+                    "Action button URL must start with http:// or https://".to_string(),
+                );
+            }
+
+            if action_buttons[..index]
+                .iter()
+                .any(|other| other.button_type == button.button_type)
+            {
+                return Err("Each action button type can only be added once".to_string());
+            }
+        }
+        business_profile.action_buttons = action_buttons;
+    }
+
+    if let Some(show_contact_options) = profile_data.show_contact_options {
+        business_profile.show_contact_options = show_contact_options;
+    }
+
+    business_profile.updated_at = current_time;
+    user_profile.business_profile = Some(business_profile);
+    user_profile.updated_at = current_time;
+
+    state_handler::update_user(&user_id, user_profile.clone())?;
+    Ok(user_profile)
+}
+
+// Business details are only public for business and creator accounts, and contact
+// options and action buttons only when the owner has chosen to show them
+pub fn public_business_profile(user_profile: &UserProfile) -> Option<BusinessProfile> {
+    if !matches!(
+        user_profile.account_type,
+        AccountType::Business | AccountType::Creator
+    ) {
+        return None;
+    }
+
+    let mut business_profile = user_profile.business_profile.clone()?;
+
+    if !business_profile.show_contact_options {
+        business_profile.contact_email = None;
+        business_profile.contact_phone = None;
+        business_profile.address = None;
+        business_profile.action_buttons = Vec::new();
+    }
+
+    Some(business_profile)
+}
+
+// Owners see their full profile; everyone else only gets the business details
+// public_business_profile lets through
+pub fn prepare_profile_for_viewer(mut user_profile: UserProfile, viewer_id: Option<&str>) -> UserProfile {
+    if viewer_id != Some(user_profile.user_id.as_str()) {
+        user_profile.business_profile = public_business_profile(&user_profile);
+    }
+
+    user_profile
+}
+
+pub fn prepare_profile_for_caller(user_profile: UserProfile) -> UserProfile {
+    let current_user = user_management::authenticate_user().ok();
+    prepare_profile_for_viewer(user_profile, current_user.as_deref())
+}
+
+pub fn prepare_profiles_for_caller(user_profiles: Vec<UserProfile>) -> Vec<UserProfile> {
+    let current_user = user_management::authenticate_user().ok();
+    user_profiles
+        .into_iter()
+        .map(|user_profile| prepare_profile_for_viewer(user_profile, current_user.as_deref()))
+        .collect()
+}
+
 pub fn get_all_profiles() -> Result<Vec<UserProfile>, String> {
     let current_user = get_current_user()?;
     let all_profiles = state_handler::get_all_users();
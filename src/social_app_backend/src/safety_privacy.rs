@@ -209,6 +209,153 @@ pub fn get_keyword_filters() -> Result<Vec<String>, String> {
     Ok(state_handler::get_keyword_filters(&user_id))
 }
 
+pub fn update_tag_settings(
+    allow_tags_from: TagPermission,
+    manually_approve_tags: bool,
+) -> Result<TagSettings, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let tag_settings = TagSettings {
+        user_id,
+        allow_tags_from,
+        manually_approve_tags,
+        updated_at: state_handler::get_current_timestamp(),
+    };
+
+    state_handler::update_tag_settings(tag_settings.clone());
+    Ok(tag_settings)
+}
+
+pub fn get_tag_settings() -> Result<TagSettings, String> {
+    let user_id = user_management::authenticate_user()?;
+    Ok(get_user_tag_settings(&user_id))
+}
+
+fn get_user_tag_settings(user_id: &str) -> TagSettings {
+    state_handler::get_tag_settings(user_id).unwrap_or(TagSettings {
+        user_id: user_id.to_string(),
+        allow_tags_from: TagPermission::Everyone,
+        manually_approve_tags: false,
+        updated_at: 0,
+    })
+}
+
+// Returns whether the tag has to wait for the tagged user's approval
+pub fn check_tag_allowed(tagger_id: &str, tagged_user_id: &str) -> Result<bool, String> {
+    if tagger_id == tagged_user_id {
+        return Ok(false);
+    }
+
+    let settings = get_user_tag_settings(tagged_user_id);
+
+    let allowed = match settings.allow_tags_from {
+        TagPermission::Everyone => true,
+        TagPermission::PeopleYouFollow => state_handler::is_following(tagged_user_id, tagger_id),
+        TagPermission::Nobody => false,
+    };
+
+    if !allowed || user_management::is_user_blocked(tagged_user_id, tagger_id) {
+        return Err(format!("You can't tag user {}", tagged_user_id));
+    }
+
+    Ok(settings.manually_approve_tags)
+}
+
+pub fn update_sensitive_content_level(level: SensitiveContentLevel) -> Result<SensitiveContentLevel, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    state_handler::set_sensitive_content_level(user_id, level.clone());
+    Ok(level)
+}
+
+pub fn get_sensitive_content_level() -> Result<SensitiveContentLevel, String> {
+    let user_id = user_management::authenticate_user()?;
+    Ok(get_viewer_sensitive_content_level(Some(&user_id)))
+}
+
+// Signed-out viewers get the default level
+fn get_viewer_sensitive_content_level(viewer_id: Option<&str>) -> SensitiveContentLevel {
+    viewer_id
+        .and_then(state_handler::get_sensitive_content_level)
+        .unwrap_or(SensitiveContentLevel::Limit)
+}
+
+// Discovery surfaces (explore, hashtags, search) drop labeled content the viewer limits
+pub fn is_hidden_from_discovery(warning: Option<&ContentWarning>, viewer_id: Option<&str>) -> bool {
+    let Some(warning) = warning else {
+        return false;
+    };
+
+    match get_viewer_sensitive_content_level(viewer_id) {
+        SensitiveContentLevel::Allow => false,
+        SensitiveContentLevel::Limit => *warning != ContentWarning::Sensitive,
+        SensitiveContentLevel::LimitMore => true,
+    }
+}
+
+// Screened for when the post owner turns on hide_offensive
+const OFFENSIVE_TERMS: &[&str] = &[
+    "idiot", "stupid", "moron", "loser", "ugly", "kill yourself", "kys", "trash",
+];
+
+// Owners can always comment on their own posts
+pub fn check_comments_allowed(owner_id: &str, commenter_id: &str) -> Result<(), String> {
+    if owner_id == commenter_id {
+        return Ok(());
+    }
+
+    if let Some(controls) = state_handler::get_comment_controls(owner_id) {
+        if !controls.allow_comments {
+            return Err("Comments are turned off for this post".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+// A comment is hidden when it matches any of the post owner's filters
+pub fn should_hide_comment(owner_id: &str, commenter_id: &str, content: &str) -> bool {
+    if owner_id == commenter_id {
+        return false;
+    }
+
+    let content = content.to_lowercase();
+    let words = tokenize(&content);
+    let matches = |keyword: &str| {
+        let keyword = keyword.trim().to_lowercase();
+        let keyword_words = tokenize(&keyword);
+        if keyword_words.is_empty() {
+            // Keywords made only of symbols or emoji can't be split into words
+            return !keyword.is_empty() && content.contains(&keyword);
+        }
+        // Whole words only; multi-word keywords match as a phrase
+        words
+            .windows(keyword_words.len())
+            .any(|window| window == keyword_words.as_slice())
+    };
+
+    if state_handler::get_keyword_filters(owner_id)
+        .iter()
+        .any(|keyword| matches(keyword))
+    {
+        return true;
+    }
+
+    match state_handler::get_comment_controls(owner_id) {
+        Some(controls) => {
+            controls.filter_keywords.iter().any(|keyword| matches(keyword))
+                || (controls.hide_offensive && OFFENSIVE_TERMS.iter().any(|term| matches(term)))
+        }
+        None => false,
+    }
+}
+
+fn tokenize(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
 pub fn enable_two_factor_auth() -> Result<(), String> {
     let user_id = user_management::authenticate_user()?;
 
@@ -0,0 +1,125 @@
+use crate::content_management;
+use crate::notifications;
+use crate::state_handler;
+use crate::types::*;
+use crate::user_management;
+use std::time::Duration;
+
+const PUBLISH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_SCHEDULE_AHEAD: u64 = 75 * 24 * 60 * 60 * 1_000_000_000; // 75 days in nanoseconds
+const MAX_SCHEDULED_ITEMS_PER_USER: usize = 50;
+
+// Starts the periodic publisher. Timers don't survive upgrades, so this runs
+// from both init and post_upgrade.
+pub fn start_publish_timer() {
+    ic_cdk_timers::set_timer_interval(PUBLISH_CHECK_INTERVAL, publish_due_items);
+}
+
+pub fn schedule_post(post_data: CreatePostRequest, publish_at: u64) -> Result<ScheduledItem, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    if matches!(post_data.content_type, ContentType::Story | ContentType::Live) {
+        return Err("Use schedule_story for stories; live streams cannot be scheduled".to_string());
+    }
+
+    content_management::validate_post_request(&post_data, &user_id)?;
+
+    if let Some(poll) = &post_data.poll {
+        content_management::validate_poll_window(poll, publish_at)?;
+    }
+
+    schedule_content(user_id, ScheduledContent::Post(post_data), publish_at)
+}
+
+pub fn schedule_story(story_data: CreateStoryRequest, publish_at: u64) -> Result<ScheduledItem, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    content_management::validate_story_request(&story_data, &user_id)?;
+
+    schedule_content(user_id, ScheduledContent::Story(story_data), publish_at)
+}
+
+pub fn get_scheduled_items() -> Result<Vec<ScheduledItem>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let mut items = state_handler::get_user_scheduled_items(&user_id);
+    items.sort_by_key(|item| item.publish_at);
+
+    Ok(items)
+}
+
+pub fn cancel_scheduled_item(schedule_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let item = state_handler::get_scheduled_item(&schedule_id)
+        .ok_or_else(|| "Scheduled item not found".to_string())?;
+
+    if item.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+
+    state_handler::remove_scheduled_item(&schedule_id);
+    Ok(())
+}
+
+fn schedule_content(user_id: String, content: ScheduledContent, publish_at: u64) -> Result<ScheduledItem, String> {
+    state_handler::get_user(&user_id).ok_or_else(|| "User profile not found".to_string())?;
+
+    let current_time = state_handler::get_current_timestamp();
+
+    if publish_at <= current_time {
+        return Err("Publish time must be in the future".to_string());
+    }
+
+    if publish_at - current_time > MAX_SCHEDULE_AHEAD {
+        return Err("Content can be scheduled at most 75 days ahead".to_string());
+    }
+
+    if state_handler::get_user_scheduled_items(&user_id).len() >= MAX_SCHEDULED_ITEMS_PER_USER {
+        return Err(format!(
+            "Maximum of {} scheduled items allowed",
+            MAX_SCHEDULED_ITEMS_PER_USER
+        ));
+    }
+
+    let schedule_id = state_handler::generate_id();
+
+    let item = ScheduledItem {
+        schedule_id: schedule_id.clone(),
+        user_id,
+        content,
+        publish_at,
+        created_at: current_time,
+    };
+
+    state_handler::insert_scheduled_item(schedule_id, item.clone());
+    Ok(item)
+}
+
+fn publish_due_items() {
+    let current_time = state_handler::get_current_timestamp();
+
+    for item in state_handler::take_due_scheduled_items(current_time) {
+        let result = match item.content {
+            ScheduledContent::Post(post_data) => {
+                content_management::create_post_for_user(&item.user_id, post_data).map(|_| ())
+            }
+            ScheduledContent::Story(story_data) => {
+                content_management::create_story_for_user(&item.user_id, story_data).map(|_| ())
+            }
+        };
+
+        // Let the creator know why their content didn't go out
+        if let Err(error) = result {
+            let _ = notifications::send_notification(
+                item.user_id,
+                NotificationType::System,
+                "Scheduled content not published".to_string(),
+                format!("Your scheduled content could not be published: {}", error),
+                None,
+                None,
+                None,
+            );
+        }
+    }
+}
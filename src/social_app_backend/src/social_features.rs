@@ -1,4 +1,5 @@
 use crate::notifications;
+use crate::safety_privacy;
 use crate::state_handler;
 use crate::types::*;
 use crate::user_management;
@@ -76,6 +77,9 @@ pub fn comment_on_post(post_id: String, content: String) -> Result<Comment, Stri
         return Err("Comment must be 2200 characters or less".to_string());
     }
 
+    safety_privacy::check_comments_allowed(&post.user_id, &user_id)?;
+    let is_hidden = safety_privacy::should_hide_comment(&post.user_id, &user_id, &content);
+
     let comment_id = state_handler::generate_id();
     let current_time = state_handler::get_current_timestamp();
 
@@ -95,6 +99,7 @@ pub fn comment_on_post(post_id: String, content: String) -> Result<Comment, Stri
         updated_at: current_time,
         is_pinned: false,
         mentions: mentions.clone(),
+        is_hidden,
     };
 
     state_handler::insert_comment(comment_id.clone(), comment.clone());
@@ -107,6 +112,11 @@ pub fn comment_on_post(post_id: String, content: String) -> Result<Comment, Stri
         Some("comment".to_string()),
     );
 
+    // Hidden comments stay quiet until the post owner approves them
+    if is_hidden {
+        return Ok(comment);
+    }
+
     // Send notification to post owner (if not commenting on own post)
     if post.user_id != user_id {
         let _ = notifications::notify_comment(
@@ -160,6 +170,9 @@ pub fn reply_to_comment(comment_id: String, content: String) -> Result<Comment,
         return Err("Reply must be 2200 characters or less".to_string());
     }
 
+    safety_privacy::check_comments_allowed(&post.user_id, &user_id)?;
+    let is_hidden = safety_privacy::should_hide_comment(&post.user_id, &user_id, &content);
+
     let reply_id = state_handler::generate_id();
     let current_time = state_handler::get_current_timestamp();
 
@@ -179,12 +192,15 @@ pub fn reply_to_comment(comment_id: String, content: String) -> Result<Comment,
         updated_at: current_time,
         is_pinned: false,
         mentions: mentions.clone(),
+        is_hidden,
     };
 
     state_handler::insert_comment(reply_id.clone(), reply.clone());
 
     // Update parent comment's reply count
-    let _ = state_handler::update_comment_reply_count(&comment_id, true);
+    if !is_hidden {
+        let _ = state_handler::update_comment_reply_count(&comment_id, true);
+    }
 
     // Log user activity
     user_management::log_user_activity(
@@ -194,6 +210,10 @@ pub fn reply_to_comment(comment_id: String, content: String) -> Result<Comment,
         Some("reply".to_string()),
     );
 
+    if is_hidden {
+        return Ok(reply);
+    }
+
     // Send notification to parent comment owner (if not replying to own comment)
     if parent_comment.user_id != user_id {
         let _ = notifications::notify_comment(
@@ -219,7 +239,12 @@ pub fn reply_to_comment(comment_id: String, content: String) -> Result<Comment,
     Ok(reply)
 }
 
-pub fn get_post_comments(post_id: String, limit: u32, offset: u32) -> Result<Vec<Comment>, String> {
+pub fn get_post_comments(
+    post_id: String,
+    limit: u32,
+    offset: u32,
+    sort: Option<CommentSortMode>,
+) -> Result<Vec<Comment>, String> {
     let user_id = user_management::authenticate_user()?;
 
     // Check if post exists and user can view it
@@ -229,28 +254,72 @@ pub fn get_post_comments(post_id: String, limit: u32, offset: u32) -> Result<Vec
         return Err("Access denied".to_string());
     }
 
+    let sort = sort.unwrap_or(CommentSortMode::Top);
     let comments = state_handler::get_post_comments(&post_id);
 
     // Filter out replies (only show top-level comments)
     let top_level_comments: Vec<Comment> = comments
         .into_iter()
         .filter(|comment| comment.parent_comment_id.is_none())
+        .filter(|comment| !comment.is_hidden || comment.user_id == user_id)
+        .filter(|comment| {
+            !matches!(sort, CommentSortMode::Following)
+                || comment.is_pinned
+                || comment.user_id == post.user_id
+                || comment.user_id == user_id
+                || state_handler::is_following(&user_id, &comment.user_id)
+        })
         .collect();
 
-    // Sort by creation time (oldest first for comments)
-    let mut sorted_comments = top_level_comments;
-    sorted_comments.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    // Pinned comments lead, then the post author's, then the chosen ranking
+    let mut scored_comments: Vec<(f64, Comment)> = top_level_comments
+        .into_iter()
+        .map(|comment| (calculate_comment_score(&comment, &user_id), comment))
+        .collect();
+
+    scored_comments.sort_by(|(a_score, a), (b_score, b)| {
+        b.is_pinned
+            .cmp(&a.is_pinned)
+            .then_with(|| (b.user_id == post.user_id).cmp(&(a.user_id == post.user_id)))
+            .then_with(|| match sort {
+                CommentSortMode::Newest => b.created_at.cmp(&a.created_at),
+                CommentSortMode::Top | CommentSortMode::Following => b_score
+                    .partial_cmp(a_score)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            })
+    });
 
     // Apply pagination
-    let start = offset as usize;
-    let end = start + limit as usize;
+    Ok(scored_comments
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .map(|(_, comment)| comment)
+        .collect())
+}
 
-    if start >= sorted_comments.len() {
-        return Ok(Vec::new());
-    }
+// Engagement weighted by how close the viewer is to the commenter, decayed by age
+fn calculate_comment_score(comment: &Comment, viewer_id: &str) -> f64 {
+    let current_time = state_handler::get_current_timestamp();
+    let age_hours =
+        current_time.saturating_sub(comment.created_at) as f64 / (1_000_000_000.0 * 60.0 * 60.0);
+
+    let engagement = comment.likes_count as f64 + comment.replies_count as f64 * 2.0;
+
+    let relationship_boost = if comment.user_id == viewer_id {
+        2.0
+    } else if state_handler::is_close_friend(viewer_id, &comment.user_id) {
+        1.5
+    } else if state_handler::is_following(viewer_id, &comment.user_id) {
+        1.0
+    } else {
+        0.0
+    };
 
-    let end = end.min(sorted_comments.len());
-    Ok(sorted_comments[start..end].to_vec())
+    // Decay over days
+    let time_factor = 1.0 / (1.0 + age_hours / 24.0);
+
+    (engagement + 1.0) * time_factor + relationship_boost
 }
 
 pub fn get_comment_replies(
@@ -278,6 +347,7 @@ pub fn get_comment_replies(
     let replies: Vec<Comment> = all_comments
         .into_iter()
         .filter(|comment| comment.parent_comment_id == Some(comment_id.clone()))
+        .filter(|comment| !comment.is_hidden || comment.user_id == user_id)
         .collect();
 
     // Sort by creation time (oldest first)
@@ -296,6 +366,257 @@ pub fn get_comment_replies(
     Ok(sorted_replies[start..end].to_vec())
 }
 
+// Comments held back by the post owner's filters, oldest first
+pub fn get_hidden_comments(post_id: String) -> Result<Vec<Comment>, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let post = state_handler::get_post(&post_id).ok_or_else(|| "Post not found".to_string())?;
+
+    if post.user_id != user_id {
+        return Err("Only the post owner can review hidden comments".to_string());
+    }
+
+    let mut hidden_comments: Vec<Comment> = state_handler::get_post_comments(&post_id)
+        .into_iter()
+        .filter(|comment| comment.is_hidden)
+        .collect();
+
+    hidden_comments.sort_by_key(|comment| comment.created_at);
+    Ok(hidden_comments)
+}
+
+pub fn approve_hidden_comment(comment_id: String) -> Result<Comment, String> {
+    let user_id = user_management::authenticate_user()?;
+    let (comment, post) = get_hidden_comment_for_owner(&comment_id, &user_id)?;
+
+    let comment = state_handler::set_comment_hidden(&comment.comment_id, false)?;
+
+    // Deliver the notifications that were held back while the comment was hidden
+    let notify_user_id = match &comment.parent_comment_id {
+        Some(parent_id) => state_handler::get_comment(parent_id).map(|parent| parent.user_id),
+        None => Some(post.user_id.clone()),
+    };
+
+    if let Some(notify_user_id) = notify_user_id.filter(|id| id != &comment.user_id) {
+        let _ = notifications::notify_comment(
+            notify_user_id,
+            comment.user_id.clone(),
+            comment.post_id.clone(),
+            comment.comment_id.clone(),
+        );
+    }
+
+    for mention in &comment.mentions {
+        if state_handler::get_user(mention).is_some() {
+            let _ = notifications::notify_mention(
+                mention.clone(),
+                comment.user_id.clone(),
+                Some(comment.post_id.clone()),
+                Some(comment.comment_id.clone()),
+            );
+        }
+    }
+
+    Ok(comment)
+}
+
+pub fn delete_hidden_comment(comment_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+    get_hidden_comment_for_owner(&comment_id, &user_id)?;
+
+    state_handler::remove_comment(&comment_id);
+    Ok(())
+}
+
+fn get_hidden_comment_for_owner(comment_id: &str, user_id: &str) -> Result<(Comment, Post), String> {
+    let comment =
+        state_handler::get_comment(comment_id).ok_or_else(|| "Comment not found".to_string())?;
+
+    let post = state_handler::get_post(&comment.post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+
+    if post.user_id != user_id {
+        return Err("Only the post owner can review hidden comments".to_string());
+    }
+
+    if !comment.is_hidden {
+        return Err("Comment is not hidden".to_string());
+    }
+
+    Ok((comment, post))
+}
+
+const COMMENT_EDIT_WINDOW: u64 = 15 * 60 * 1_000_000_000; // 15 minutes in nanoseconds
+const MAX_PINNED_COMMENTS: usize = 3;
+
+pub fn like_comment(comment_id: String) -> Result<Comment, String> {
+    let user_id = user_management::authenticate_user()?;
+    get_visible_comment(&comment_id, &user_id)?;
+
+    if state_handler::has_user_liked_comment(&comment_id, &user_id) {
+        return Err("Comment already liked".to_string());
+    }
+
+    let comment = state_handler::add_comment_like(&comment_id, user_id.clone())?;
+
+    // Send notification to comment owner (if not liking own comment)
+    if comment.user_id != user_id {
+        let _ = notifications::notify_comment_like(
+            comment.user_id.clone(),
+            user_id,
+            comment.post_id.clone(),
+            comment_id,
+        );
+    }
+
+    Ok(comment)
+}
+
+pub fn unlike_comment(comment_id: String) -> Result<Comment, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    state_handler::get_comment(&comment_id).ok_or_else(|| "Comment not found".to_string())?;
+
+    if !state_handler::has_user_liked_comment(&comment_id, &user_id) {
+        return Err("Comment not liked".to_string());
+    }
+
+    state_handler::remove_comment_like(&comment_id, &user_id)
+}
+
+pub fn edit_comment(comment_id: String, content: String) -> Result<Comment, String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let mut comment =
+        state_handler::get_comment(&comment_id).ok_or_else(|| "Comment not found".to_string())?;
+
+    if comment.user_id != user_id {
+        return Err("Access denied".to_string());
+    }
+
+    let current_time = state_handler::get_current_timestamp();
+    if current_time.saturating_sub(comment.created_at) > COMMENT_EDIT_WINDOW {
+        return Err("Comments can only be edited within 15 minutes of posting".to_string());
+    }
+
+    if content.trim().is_empty() {
+        return Err("Comment cannot be empty".to_string());
+    }
+
+    if content.len() > 2200 {
+        return Err("Comment must be 2200 characters or less".to_string());
+    }
+
+    let post = state_handler::get_post(&comment.post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+
+    // Edited text goes through the post owner's filters again
+    if !comment.is_hidden && safety_privacy::should_hide_comment(&post.user_id, &user_id, &content) {
+        comment = state_handler::set_comment_hidden(&comment_id, true)?;
+    }
+
+    comment.mentions = extract_mentions(&content);
+    comment.content = content;
+    comment.updated_at = current_time;
+
+    state_handler::update_comment(comment.clone());
+    Ok(comment)
+}
+
+// Comment authors and the post owner can delete a comment; its replies go with it
+pub fn delete_comment(comment_id: String) -> Result<(), String> {
+    let user_id = user_management::authenticate_user()?;
+
+    let comment =
+        state_handler::get_comment(&comment_id).ok_or_else(|| "Comment not found".to_string())?;
+
+    let is_post_owner = state_handler::get_post(&comment.post_id)
+        .is_some_and(|post| post.user_id == user_id);
+
+    if comment.user_id != user_id && !is_post_owner {
+        return Err("Access denied".to_string());
+    }
+
+    state_handler::remove_comment(&comment_id);
+    Ok(())
+}
+
+pub fn pin_comment(comment_id: String) -> Result<Comment, String> {
+    let user_id = user_management::authenticate_user()?;
+    let (mut comment, post) = get_owned_post_comment(&comment_id, &user_id)?;
+
+    if comment.is_pinned {
+        return Err("Comment is already pinned".to_string());
+    }
+
+    if comment.is_hidden {
+        return Err("Hidden comments cannot be pinned".to_string());
+    }
+
+    if comment.parent_comment_id.is_some() {
+        return Err("Only top-level comments can be pinned".to_string());
+    }
+
+    let pinned_count = state_handler::get_post_comments(&post.post_id)
+        .iter()
+        .filter(|c| c.is_pinned)
+        .count();
+
+    if pinned_count >= MAX_PINNED_COMMENTS {
+        return Err(format!("You can pin up to {} comments", MAX_PINNED_COMMENTS));
+    }
+
+    comment.is_pinned = true;
+    state_handler::update_comment(comment.clone());
+    Ok(comment)
+}
+
+pub fn unpin_comment(comment_id: String) -> Result<Comment, String> {
+    let user_id = user_management::authenticate_user()?;
+    let (mut comment, _) = get_owned_post_comment(&comment_id, &user_id)?;
+
+    if !comment.is_pinned {
+        return Err("Comment is not pinned".to_string());
+    }
+
+    comment.is_pinned = false;
+    state_handler::update_comment(comment.clone());
+    Ok(comment)
+}
+
+// Hidden comments only exist for their author and the post owner
+fn get_visible_comment(comment_id: &str, user_id: &str) -> Result<Comment, String> {
+    let comment =
+        state_handler::get_comment(comment_id).ok_or_else(|| "Comment not found".to_string())?;
+
+    let post = state_handler::get_post(&comment.post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+
+    if !can_view_post(&post, Some(user_id)) {
+        return Err("Access denied".to_string());
+    }
+
+    if comment.is_hidden && comment.user_id != user_id && post.user_id != user_id {
+        return Err("Comment not found".to_string());
+    }
+
+    Ok(comment)
+}
+
+fn get_owned_post_comment(comment_id: &str, user_id: &str) -> Result<(Comment, Post), String> {
+    let comment =
+        state_handler::get_comment(comment_id).ok_or_else(|| "Comment not found".to_string())?;
+
+    let post = state_handler::get_post(&comment.post_id)
+        .ok_or_else(|| "Post not found".to_string())?;
+
+    if post.user_id != user_id {
+        return Err("Only the post owner can pin comments".to_string());
+    }
+
+    Ok((comment, post))
+}
+
 pub fn follow_user(user_id: String) -> Result<(), String> {
     let current_user = user_management::authenticate_user()?;
 
@@ -558,6 +879,7 @@ pub fn share_post(post_id: String, target_user_ids: Vec<String>) -> Result<(), S
             is_vanish_mode: false,
             created_at: current_time,
             expires_at: None,
+            story: None,
         };
 
         state_handler::insert_message(conversation_id, share_message);
@@ -590,6 +912,10 @@ pub fn share_post(post_id: String, target_user_ids: Vec<String>) -> Result<(), S
 
 // Helper functions
 fn can_view_post(post: &Post, current_user_id: Option<&str>) -> bool {
+    if current_user_id.is_some_and(|user_id| post.is_author(user_id)) {
+        return true;
+    }
+
     match post.visibility {
         PostVisibility::Public => true,
         PostVisibility::Private => {
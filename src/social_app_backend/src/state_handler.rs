@@ -1,23 +1,50 @@
 use crate::types::{self, *};
+use candid::{decode_one, encode_one};
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, Storable};
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+const SCHEDULED_ITEMS_MEMORY_ID: MemoryId = MemoryId::new(0);
+const MEDIA_CHUNKS_MEMORY_ID: MemoryId = MemoryId::new(1);
+const MEDIA_METADATA_MEMORY_ID: MemoryId = MemoryId::new(2);
+const MEDIA_USAGE_MEMORY_ID: MemoryId = MemoryId::new(3);
+const MEDIA_UPLOADS_MEMORY_ID: MemoryId = MemoryId::new(4);
+const MEDIA_UPLOAD_CHUNKS_MEMORY_ID: MemoryId = MemoryId::new(5);
+
+pub const MEDIA_URL_PREFIX: &str = "/media/";
 
 // Global state storage
 thread_local! {
     static USERS: RefCell<HashMap<String, UserProfile>> = RefCell::new(HashMap::new());
     static POSTS: RefCell<HashMap<String, Post>> = RefCell::new(HashMap::new());
+    static POST_REVISIONS: RefCell<HashMap<String, Vec<PostRevision>>> = RefCell::new(HashMap::new()); // post_id -> revisions (oldest first)
+    static POST_DRAFTS: RefCell<HashMap<String, Vec<PostDraft>>> = RefCell::new(HashMap::new()); // user_id -> drafts
     static COMMENTS: RefCell<HashMap<String, Comment>> = RefCell::new(HashMap::new());
     static LIKES: RefCell<HashMap<String, Vec<Like>>> = RefCell::new(HashMap::new());
     static RELATIONSHIPS: RefCell<HashMap<String, Vec<Relationship>>> = RefCell::new(HashMap::new());
     static CONVERSATIONS: RefCell<HashMap<String, Conversation>> = RefCell::new(HashMap::new());
     static MESSAGES: RefCell<HashMap<String, Vec<Message>>> = RefCell::new(HashMap::new());
     static STORIES: RefCell<HashMap<String, Story>> = RefCell::new(HashMap::new());
+    static ARCHIVED_STORIES: RefCell<HashMap<String, Story>> = RefCell::new(HashMap::new()); // story_id -> expired story
+    static STORY_ARCHIVE_SETTINGS: RefCell<HashMap<String, StoryArchiveSettings>> = RefCell::new(HashMap::new());
+    static STORY_HIDDEN_FROM: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> user_ids who can't see their stories
+    static STORY_REPLY_SETTINGS: RefCell<HashMap<String, StoryReplySetting>> = RefCell::new(HashMap::new()); // user_id -> who may reply to their stories
+    static STORY_VIEWER_HISTORY: RefCell<HashMap<String, Vec<u64>>> = RefCell::new(HashMap::new()); // story_id -> bloom filter of viewers dropped from Story.viewers
+    static STICKER_RESPONSES: RefCell<HashMap<String, Vec<StickerResponse>>> = RefCell::new(HashMap::new()); // story_id -> responses to its stickers
     static HIGHLIGHTS: RefCell<HashMap<String, Highlight>> = RefCell::new(HashMap::new());
+    static USER_HIGHLIGHTS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> highlight_ids (profile order)
     static NOTIFICATIONS: RefCell<HashMap<String, Vec<Notification>>> = RefCell::new(HashMap::new());
     static SAVED_POSTS: RefCell<HashMap<String, Vec<SavedPost>>> = RefCell::new(HashMap::new());
     static SHOPS: RefCell<HashMap<String, Shop>> = RefCell::new(HashMap::new());
     static PRODUCTS: RefCell<HashMap<String, Product>> = RefCell::new(HashMap::new());
     static LIVE_STREAMS: RefCell<HashMap<String, LiveStream>> = RefCell::new(HashMap::new());
+    static AUDIO_TRACKS: RefCell<HashMap<String, AudioTrack>> = RefCell::new(HashMap::new());
+    static AUDIO_POSTS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // track_id -> post_ids
     static HASHTAGS: RefCell<HashMap<String, Hashtag>> = RefCell::new(HashMap::new());
     static REPORTS: RefCell<HashMap<String, Report>> = RefCell::new(HashMap::new());
     static CLOSE_FRIENDS: RefCell<HashMap<String, Vec<CloseFriend>>> = RefCell::new(HashMap::new());
@@ -27,7 +54,16 @@ thread_local! {
     static USER_FOLLOWERS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> follower_ids
     static USER_FOLLOWING: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> following_ids
     static USER_POSTS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> post_ids
+    static POST_REMIXES: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // source post_id -> remix post_ids
+    static TAGGED_POSTS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> post_ids they are tagged in, approved or pending
+    static OPEN_POLLS: RefCell<BTreeSet<(u64, String)>> = const { RefCell::new(BTreeSet::new()) }; // (ends_at, post_id) of polls not closed yet
+    static POLL_VOTES: RefCell<HashMap<String, HashMap<String, u32>>> = RefCell::new(HashMap::new()); // post_id -> user_id -> option index
+    static SENSITIVE_CONTENT_LEVELS: RefCell<HashMap<String, SensitiveContentLevel>> = RefCell::new(HashMap::new());
+    static TAG_SETTINGS: RefCell<HashMap<String, TagSettings>> = RefCell::new(HashMap::new());
+    static COLLAB_INVITES: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> post_ids awaiting a response
+    static PINNED_POSTS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> post_ids (pin order)
     static HASHTAG_POSTS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // hashtag -> post_ids
+    static FOLLOWED_HASHTAGS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> hashtags
     static BLOCKED_USERS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> blocked_user_ids
     static MUTED_USERS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> muted_user_ids
     static RESTRICTED_USERS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new()); // user_id -> restricted_user_ids
@@ -38,6 +74,152 @@ thread_local! {
     static SECURITY_SETTINGS: RefCell<HashMap<String, crate::safety_privacy::SecuritySettings>> = RefCell::new(HashMap::new());
     static TIME_LIMIT_SETTINGS: RefCell<HashMap<String, crate::safety_privacy::TimeLimitSettings>> = RefCell::new(HashMap::new());
     static NOTIFICATION_SETTINGS: RefCell<HashMap<String, types::NotificationSettings>> = RefCell::new(HashMap::new());
+    static ID_COUNTER: RefCell<u64> = const { RefCell::new(0) };
+}
+
+// Stable memory storage (survives upgrades)
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+    static SCHEDULED_ITEMS: RefCell<StableBTreeMap<String, ScheduledItem, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(SCHEDULED_ITEMS_MEMORY_ID))),
+    );
+    static MEDIA_CHUNKS: RefCell<StableBTreeMap<String, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MEDIA_CHUNKS_MEMORY_ID))),
+    ); // "<hash>/<chunk_index>" -> bytes
+    static MEDIA_METADATA: RefCell<StableBTreeMap<String, StoredMedia, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MEDIA_METADATA_MEMORY_ID))),
+    ); // hash -> metadata
+    static MEDIA_USAGE: RefCell<StableBTreeMap<String, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MEDIA_USAGE_MEMORY_ID))),
+    ); // user_id -> stored bytes
+    static MEDIA_UPLOADS: RefCell<StableBTreeMap<String, MediaUpload, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MEDIA_UPLOADS_MEMORY_ID))),
+    ); // upload_id -> upload in progress
+    static MEDIA_UPLOAD_CHUNKS: RefCell<StableBTreeMap<String, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MEDIA_UPLOAD_CHUNKS_MEMORY_ID))),
+    ); // "<upload_id>/<chunk_index>" -> bytes received so far
+}
+
+impl Storable for StoredMedia {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_one(self).expect("Failed to encode media metadata"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_one(&bytes).expect("Failed to decode media metadata")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for MediaUpload {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_one(self).expect("Failed to encode media upload"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_one(&bytes).expect("Failed to decode media upload")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for ScheduledItem {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(encode_one(self).expect("Failed to encode scheduled item"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        // Items scheduled before structured media existed carry bare URLs
+        decode_one(&bytes).unwrap_or_else(|_| {
+            decode_one::<LegacyScheduledItem>(&bytes)
+                .expect("Failed to decode scheduled item")
+                .into()
+        })
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Pre-MediaItem layouts, only used to migrate stored scheduled items
+#[derive(candid::CandidType, serde::Deserialize)]
+struct LegacyScheduledItem {
+    schedule_id: String,
+    user_id: String,
+    content: LegacyScheduledContent,
+    publish_at: u64,
+    created_at: u64,
+}
+
+#[derive(candid::CandidType, serde::Deserialize)]
+enum LegacyScheduledContent {
+    Post(LegacyCreatePostRequest),
+    Story(LegacyCreateStoryRequest),
+}
+
+#[derive(candid::CandidType, serde::Deserialize)]
+struct LegacyCreatePostRequest {
+    content_type: ContentType,
+    media_urls: Vec<String>,
+    caption: String,
+    hashtags: Vec<String>,
+    tagged_users: Vec<String>,
+    location: Option<LocationTag>,
+    visibility: PostVisibility,
+    music_info: Option<MusicInfo>,
+    product_tags: Vec<ProductTag>,
+}
+
+#[derive(candid::CandidType, serde::Deserialize)]
+struct LegacyCreateStoryRequest {
+    media_url: String,
+    story_type: StoryType,
+    text_overlay: Option<String>,
+    stickers: Vec<Sticker>,
+    music_info: Option<MusicInfo>,
+}
+
+impl From<LegacyScheduledItem> for ScheduledItem {
+    fn from(legacy: LegacyScheduledItem) -> Self {
+        let content = match legacy.content {
+            LegacyScheduledContent::Post(post) => ScheduledContent::Post(CreatePostRequest {
+                content_type: post.content_type,
+                media: post.media_urls.into_iter().map(MediaItem::from_url).collect(),
+                caption: post.caption,
+                hashtags: post.hashtags,
+                tagged_users: post.tagged_users,
+                location: post.location,
+                visibility: post.visibility,
+                music_info: post.music_info,
+                product_tags: post.product_tags,
+                collaborators: None,
+                remix_of: None,
+                remix_type: None,
+                allow_remixes: None,
+                hide_like_count: None,
+                content_warning: None,
+                poll: None,
+            }),
+            LegacyScheduledContent::Story(story) => ScheduledContent::Story(CreateStoryRequest {
+                media: MediaItem::from_url(story.media_url),
+                story_type: story.story_type,
+                text_overlay: story.text_overlay,
+                stickers: story.stickers,
+                music_info: story.music_info,
+                audience: None,
+                content_warning: None,
+            }),
+        };
+
+        Self {
+            schedule_id: legacy.schedule_id,
+            user_id: legacy.user_id,
+            content,
+            publish_at: legacy.publish_at,
+            created_at: legacy.created_at,
+        }
+    }
 }
 
 // User operations
@@ -66,6 +248,16 @@ pub fn user_exists(user_id: &str) -> bool {
     USERS.with(|users| users.borrow().contains_key(user_id))
 }
 
+pub fn get_user_by_username(username: &str) -> Option<UserProfile> {
+    USERS.with(|users| {
+        users
+            .borrow()
+            .values()
+            .find(|user| user.username.eq_ignore_ascii_case(username))
+            .cloned()
+    })
+}
+
 pub fn username_exists(username: &str) -> bool {
     USERS.with(|users| {
         users
@@ -82,13 +274,31 @@ pub fn insert_post(post_id: String, post: Post) {
         posts.borrow_mut().insert(post_id.clone(), post);
     });
 
-    // Add to user's posts
+    // Add to user's posts if this is a new post
     USER_POSTS.with(|user_posts| {
-        user_posts
-            .borrow_mut()
-            .entry(user_id)
-            .or_insert_with(Vec::new)
-            .push(post_id);
+        let mut user_posts = user_posts.borrow_mut();
+        let post_ids = user_posts.entry(user_id).or_insert_with(Vec::new);
+        if !post_ids.contains(&post_id) {
+            post_ids.push(post_id);
+        }
+    });
+}
+
+pub fn add_user_post(user_id: &str, post_id: &str) {
+    USER_POSTS.with(|user_posts| {
+        let mut user_posts = user_posts.borrow_mut();
+        let post_ids = user_posts.entry(user_id.to_string()).or_default();
+        if !post_ids.iter().any(|id| id == post_id) {
+            post_ids.push(post_id.to_string());
+        }
+    });
+}
+
+pub fn remove_user_post(user_id: &str, post_id: &str) {
+    USER_POSTS.with(|user_posts| {
+        if let Some(post_ids) = user_posts.borrow_mut().get_mut(user_id) {
+            post_ids.retain(|id| id != post_id);
+        }
     });
 }
 
@@ -116,15 +326,772 @@ pub fn get_user_posts(user_id: &str) -> Vec<Post> {
 }
 
 pub fn delete_post(post_id: &str) -> Result<(), String> {
+    let post = POSTS
+        .with(|posts| posts.borrow_mut().remove(post_id))
+        .ok_or_else(|| "Post not found".to_string())?;
+
+    release_media_items(&post.media);
+    if let Some(music_info) = &post.music_info {
+        release_audio_use(&music_info.track_id, Some(post_id));
+    }
+
+    // Unlink from the source reel; remixes of this post keep their attribution
+    if let Some(remix_of) = &post.remix_of {
+        remove_post_remix(&remix_of.source_post_id, post_id);
+    }
+    POST_REMIXES.with(|remixes| {
+        remixes.borrow_mut().remove(post_id);
+    });
+
+    // Take the post off every author's profile and drop open invitations
+    remove_user_post(&post.user_id, post_id);
+    for collaborator in &post.collaborators {
+        remove_user_post(&collaborator.user_id, post_id);
+        remove_collab_invite(&collaborator.user_id, post_id);
+    }
+
+    // Edit history belongs to the post
+    POST_REVISIONS.with(|revisions| {
+        revisions.borrow_mut().remove(post_id);
+    });
+
+    for hashtag in &post.hashtags {
+        remove_hashtag(hashtag, post_id);
+    }
+
+    for tagged_user_id in post.tagged_users.iter().chain(&post.pending_tagged_users) {
+        remove_tagged_post(tagged_user_id, post_id);
+    }
+
+    POLL_VOTES.with(|votes| {
+        votes.borrow_mut().remove(post_id);
+    });
+    if let Some(poll) = &post.poll {
+        remove_open_poll(poll.ends_at, post_id);
+    }
+
+    Ok(())
+}
+
+// Poll operations
+pub fn set_poll_vote(post_id: String, user_id: String, option_index: u32) -> Option<u32> {
+    POLL_VOTES.with(|votes| {
+        votes
+            .borrow_mut()
+            .entry(post_id)
+            .or_default()
+            .insert(user_id, option_index)
+    })
+}
+
+pub fn get_poll_vote(post_id: &str, user_id: &str) -> Option<u32> {
+    POLL_VOTES.with(|votes| {
+        votes
+            .borrow()
+            .get(post_id)
+            .and_then(|post_votes| post_votes.get(user_id).copied())
+    })
+}
+
+pub fn add_open_poll(ends_at: u64, post_id: String) {
+    OPEN_POLLS.with(|open_polls| {
+        open_polls.borrow_mut().insert((ends_at, post_id));
+    });
+}
+
+fn remove_open_poll(ends_at: u64, post_id: &str) {
+    OPEN_POLLS.with(|open_polls| {
+        open_polls.borrow_mut().remove(&(ends_at, post_id.to_string()));
+    });
+}
+
+// Removes and returns the posts whose poll has reached its end time
+pub fn take_due_poll_post_ids(current_time: u64) -> Vec<String> {
+    OPEN_POLLS.with(|open_polls| {
+        let mut open_polls = open_polls.borrow_mut();
+        let still_open = open_polls.split_off(&(current_time.saturating_add(1), String::new()));
+        std::mem::replace(&mut *open_polls, still_open)
+            .into_iter()
+            .map(|(_, post_id)| post_id)
+            .collect()
+    })
+}
+
+// Post revision operations
+pub fn add_post_revision(post_id: String, revision: PostRevision) {
+    POST_REVISIONS.with(|revisions| {
+        revisions
+            .borrow_mut()
+            .entry(post_id)
+            .or_insert_with(Vec::new)
+            .push(revision);
+    });
+}
+
+pub fn get_post_revisions(post_id: &str) -> Vec<PostRevision> {
+    POST_REVISIONS.with(|revisions| revisions.borrow().get(post_id).cloned().unwrap_or_default())
+}
+
+// Pinned post operations
+pub fn pin_post(user_id: String, post_id: String) {
+    PINNED_POSTS.with(|pinned| {
+        let mut pinned = pinned.borrow_mut();
+        let post_ids = pinned.entry(user_id).or_insert_with(Vec::new);
+        if !post_ids.contains(&post_id) {
+            post_ids.push(post_id.clone());
+        }
+    });
+
     POSTS.with(|posts| {
-        if posts.borrow_mut().remove(post_id).is_some() {
-            Ok(())
+        if let Some(post) = posts.borrow_mut().get_mut(&post_id) {
+            post.is_pinned = true;
+        }
+    });
+}
+
+pub fn unpin_post(user_id: &str, post_id: &str) -> bool {
+    let was_pinned = PINNED_POSTS.with(|pinned| {
+        if let Some(post_ids) = pinned.borrow_mut().get_mut(user_id) {
+            let initial_len = post_ids.len();
+            post_ids.retain(|id| id != post_id);
+            post_ids.len() < initial_len
         } else {
-            Err("Post not found".to_string())
+            false
+        }
+    });
+
+    POSTS.with(|posts| {
+        if let Some(post) = posts.borrow_mut().get_mut(post_id) {
+            post.is_pinned = false;
+        }
+    });
+
+    was_pinned
+}
+
+pub fn get_pinned_post_ids(user_id: &str) -> Vec<String> {
+    PINNED_POSTS.with(|pinned| pinned.borrow().get(user_id).cloned().unwrap_or_default())
+}
+
+pub fn delete_user_pinned_posts(user_id: &str) -> Result<(), String> {
+    PINNED_POSTS.with(|pinned| {
+        pinned.borrow_mut().remove(user_id);
+    });
+    Ok(())
+}
+
+// Media storage operations
+// Stored blobs are keyed by hash and staged uploads by upload_id
+fn media_chunk_key(id: &str, chunk_index: u32) -> String {
+    format!("{}/{:06}", id, chunk_index)
+}
+
+pub fn media_url(hash: &str) -> String {
+    format!("{}{}", MEDIA_URL_PREFIX, hash)
+}
+
+// Returns the content hash for canister-hosted media URLs, None for external URLs
+pub fn media_hash_from_url(url: &str) -> Option<&str> {
+    url.strip_prefix(MEDIA_URL_PREFIX).filter(|hash| !hash.is_empty())
+}
+
+// Uploads in progress are staged in stable memory chunk by chunk so large
+// uploads never sit on the heap
+pub fn insert_media_upload(upload: MediaUpload) {
+    MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow_mut().insert(upload.upload_id.clone(), upload);
+    });
+}
+
+pub fn get_media_upload(upload_id: &str) -> Option<MediaUpload> {
+    MEDIA_UPLOADS.with(|uploads| uploads.borrow().get(&upload_id.to_string()))
+}
+
+pub fn get_user_media_uploads(user_id: &str) -> Vec<MediaUpload> {
+    MEDIA_UPLOADS.with(|uploads| {
+        uploads
+            .borrow()
+            .iter()
+            .map(|(_, upload)| upload)
+            .filter(|upload| upload.user_id == user_id)
+            .collect()
+    })
+}
+
+pub fn get_all_media_uploads() -> Vec<MediaUpload> {
+    MEDIA_UPLOADS.with(|uploads| uploads.borrow().iter().map(|(_, upload)| upload).collect())
+}
+
+pub fn set_media_upload_chunk(upload_id: &str, chunk_index: u32, data: Vec<u8>) {
+    MEDIA_UPLOAD_CHUNKS.with(|upload_chunks| {
+        upload_chunks
+            .borrow_mut()
+            .insert(media_chunk_key(upload_id, chunk_index), data);
+    });
+}
+
+pub fn get_media_upload_chunk(upload_id: &str, chunk_index: u32) -> Option<Vec<u8>> {
+    MEDIA_UPLOAD_CHUNKS.with(|upload_chunks| {
+        upload_chunks
+            .borrow()
+            .get(&media_chunk_key(upload_id, chunk_index))
+    })
+}
+
+// Chunk sizes are checked as they arrive, so every chunk present means the
+// whole upload is there
+pub fn has_all_media_upload_chunks(upload: &MediaUpload) -> bool {
+    MEDIA_UPLOAD_CHUNKS.with(|upload_chunks| {
+        let upload_chunks = upload_chunks.borrow();
+        (0..upload.chunk_count)
+            .all(|index| upload_chunks.contains_key(&media_chunk_key(&upload.upload_id, index)))
+    })
+}
+
+pub fn remove_media_upload(upload_id: &str) {
+    let Some(upload) = MEDIA_UPLOADS.with(|uploads| uploads.borrow_mut().remove(&upload_id.to_string())) else {
+        return;
+    };
+
+    MEDIA_UPLOAD_CHUNKS.with(|upload_chunks| {
+        let mut upload_chunks = upload_chunks.borrow_mut();
+        for index in 0..upload.chunk_count {
+            upload_chunks.remove(&media_chunk_key(upload_id, index));
+        }
+    });
+}
+
+// Moves a finished upload's chunks under the blob's hash and records the blob
+pub fn promote_media_upload(upload: &MediaUpload, media: StoredMedia) {
+    MEDIA_UPLOADS.with(|uploads| {
+        uploads.borrow_mut().remove(&upload.upload_id);
+    });
+
+    MEDIA_UPLOAD_CHUNKS.with(|upload_chunks| {
+        let mut upload_chunks = upload_chunks.borrow_mut();
+        MEDIA_CHUNKS.with(|media_chunks| {
+            let mut media_chunks = media_chunks.borrow_mut();
+            for index in 0..upload.chunk_count {
+                if let Some(chunk) = upload_chunks.remove(&media_chunk_key(&upload.upload_id, index)) {
+                    media_chunks.insert(media_chunk_key(&media.hash, index), chunk);
+                }
+            }
+        });
+    });
+
+    update_media(media);
+}
+
+pub fn update_media(media: StoredMedia) {
+    MEDIA_METADATA.with(|metadata| {
+        metadata.borrow_mut().insert(media.hash.clone(), media);
+    });
+}
+
+pub fn get_media(hash: &str) -> Option<StoredMedia> {
+    MEDIA_METADATA.with(|metadata| metadata.borrow().get(&hash.to_string()))
+}
+
+pub fn get_all_media() -> Vec<StoredMedia> {
+    MEDIA_METADATA.with(|metadata| metadata.borrow().iter().map(|(_, media)| media).collect())
+}
+
+pub fn get_media_chunk(hash: &str, chunk_index: u32) -> Option<Vec<u8>> {
+    MEDIA_CHUNKS.with(|media_chunks| media_chunks.borrow().get(&media_chunk_key(hash, chunk_index)))
+}
+
+// Deletes the blob and gives its size back to every owner's quota
+pub fn delete_media(hash: &str) {
+    let Some(media) = MEDIA_METADATA.with(|metadata| metadata.borrow_mut().remove(&hash.to_string())) else {
+        return;
+    };
+
+    MEDIA_CHUNKS.with(|media_chunks| {
+        let mut media_chunks = media_chunks.borrow_mut();
+        for index in 0..media.chunk_count {
+            media_chunks.remove(&media_chunk_key(hash, index));
+        }
+    });
+
+    for owner_id in &media.owners {
+        let used_bytes = get_media_usage(owner_id);
+        if used_bytes > 0 {
+            set_media_usage(owner_id, used_bytes.saturating_sub(media.size));
+        }
+    }
+}
+
+pub fn get_media_usage(user_id: &str) -> u64 {
+    MEDIA_USAGE.with(|usage| usage.borrow().get(&user_id.to_string()).unwrap_or(0))
+}
+
+pub fn set_media_usage(user_id: &str, used_bytes: u64) {
+    MEDIA_USAGE.with(|usage| {
+        usage.borrow_mut().insert(user_id.to_string(), used_bytes);
+    });
+}
+
+pub fn delete_user_media_usage(user_id: &str) -> Result<(), String> {
+    MEDIA_USAGE.with(|usage| {
+        usage.borrow_mut().remove(&user_id.to_string());
+    });
+    Ok(())
+}
+
+// Reference counting for canister-hosted media; external URLs are ignored
+pub fn retain_media_url(url: &str) {
+    if let Some(mut media) = media_hash_from_url(url).and_then(get_media) {
+        media.ref_count += 1;
+        update_media(media);
+    }
+}
+
+pub fn release_media_url(url: &str) {
+    if let Some(mut media) = media_hash_from_url(url).and_then(get_media) {
+        media.ref_count = media.ref_count.saturating_sub(1);
+        update_media(media);
+    }
+}
+
+pub fn retain_media_items(items: &[MediaItem]) {
+    for item in items {
+        retain_media_url(&item.url);
+    }
+}
+
+pub fn release_media_items(items: &[MediaItem]) {
+    for item in items {
+        release_media_url(&item.url);
+    }
+}
+
+// Lookups from a blob URL back to the content referencing it, used to decide
+// who may read the blob
+pub fn get_posts_with_media(url: &str) -> Vec<Post> {
+    POSTS.with(|posts| {
+        posts
+            .borrow()
+            .values()
+            .filter(|post| post.media.iter().any(|item| item.url == url))
+            .cloned()
+            .collect()
+    })
+}
+
+// Active and archived stories
+pub fn get_stories_with_media(url: &str) -> Vec<Story> {
+    let active: Vec<Story> = STORIES.with(|stories| {
+        stories
+            .borrow()
+            .values()
+            .filter(|story| story.media.url == url)
+            .cloned()
+            .collect()
+    });
+    let archived: Vec<Story> = ARCHIVED_STORIES.with(|archived| {
+        archived
+            .borrow()
+            .values()
+            .filter(|story| story.media.url == url)
+            .cloned()
+            .collect()
+    });
+
+    active.into_iter().chain(archived).collect()
+}
+
+pub fn get_users_with_profile_picture(url: &str) -> Vec<UserProfile> {
+    USERS.with(|users| {
+        users
+            .borrow()
+            .values()
+            .filter(|user| user.profile_picture.as_deref() == Some(url))
+            .cloned()
+            .collect()
+    })
+}
+
+// Whether the media was sent in one of the user's conversations
+pub fn is_media_in_user_conversations(user_id: &str, url: &str) -> bool {
+    let conversation_ids: Vec<String> = CONVERSATIONS.with(|conversations| {
+        conversations
+            .borrow()
+            .values()
+            .filter(|conversation| conversation.participants.iter().any(|id| id == user_id))
+            .map(|conversation| conversation.conversation_id.clone())
+            .collect()
+    });
+
+    MESSAGES.with(|messages| {
+        let messages = messages.borrow();
+        conversation_ids.iter().any(|conversation_id| {
+            messages.get(conversation_id).is_some_and(|conversation_messages| {
+                conversation_messages
+                    .iter()
+                    .any(|message| message.media_url.as_deref() == Some(url))
+            })
+        })
+    })
+}
+
+// Blob metadata and quota usage live in stable memory, but most content that
+// references blobs lives on the heap and does not survive an upgrade. Recount
+// references from whatever content is left so the GC can reclaim orphans, and
+// recompute every owner's usage from the blobs they own.
+pub fn rebuild_media_references() {
+    let mut ref_counts: HashMap<String, u64> = HashMap::new();
+    let mut count_url = |url: &str| {
+        if let Some(hash) = media_hash_from_url(url) {
+            *ref_counts.entry(hash.to_string()).or_default() += 1;
+        }
+    };
+
+    USERS.with(|users| {
+        for user in users.borrow().values() {
+            if let Some(profile_picture) = &user.profile_picture {
+                count_url(profile_picture);
+            }
+        }
+    });
+    POSTS.with(|posts| {
+        for post in posts.borrow().values() {
+            post.media.iter().for_each(|item| count_url(&item.url));
+        }
+    });
+    POST_DRAFTS.with(|drafts| {
+        for draft in drafts.borrow().values().flatten() {
+            draft.post_data.media.iter().for_each(|item| count_url(&item.url));
+        }
+    });
+    STORIES.with(|stories| {
+        stories.borrow().values().for_each(|story| count_url(&story.media.url));
+    });
+    ARCHIVED_STORIES.with(|archived| {
+        archived.borrow().values().for_each(|story| count_url(&story.media.url));
+    });
+    MESSAGES.with(|messages| {
+        for message in messages.borrow().values().flatten() {
+            if let Some(media_url) = &message.media_url {
+                count_url(media_url);
+            }
+        }
+    });
+    SCHEDULED_ITEMS.with(|items| {
+        for (_, item) in items.borrow().iter() {
+            scheduled_content_media(&item.content).iter().for_each(|media| count_url(&media.url));
+        }
+    });
+
+    let mut usage: HashMap<String, u64> = HashMap::new();
+    for mut media in get_all_media() {
+        for owner_id in &media.owners {
+            *usage.entry(owner_id.clone()).or_default() += media.size;
+        }
+        media.ref_count = ref_counts.get(&media.hash).copied().unwrap_or(0);
+        update_media(media);
+    }
+
+    MEDIA_USAGE.with(|media_usage| {
+        let mut media_usage = media_usage.borrow_mut();
+        let user_ids: Vec<String> = media_usage.iter().map(|(user_id, _)| user_id).collect();
+        for user_id in user_ids {
+            media_usage.remove(&user_id);
+        }
+        for (user_id, used_bytes) in usage {
+            media_usage.insert(user_id, used_bytes);
+        }
+    });
+}
+
+fn scheduled_content_media(content: &ScheduledContent) -> &[MediaItem] {
+    match content {
+        ScheduledContent::Post(post_data) => &post_data.media,
+        ScheduledContent::Story(story_data) => std::slice::from_ref(&story_data.media),
+    }
+}
+
+// Remix operations
+pub fn add_post_remix(source_post_id: &str, remix_post_id: &str) {
+    POST_REMIXES.with(|remixes| {
+        let mut remixes = remixes.borrow_mut();
+        let post_ids = remixes.entry(source_post_id.to_string()).or_default();
+        if !post_ids.iter().any(|id| id == remix_post_id) {
+            post_ids.push(remix_post_id.to_string());
+        }
+    });
+
+    POSTS.with(|posts| {
+        if let Some(source) = posts.borrow_mut().get_mut(source_post_id) {
+            source.remixes_count += 1;
+        }
+    });
+}
+
+pub fn remove_post_remix(source_post_id: &str, remix_post_id: &str) {
+    POST_REMIXES.with(|remixes| {
+        if let Some(post_ids) = remixes.borrow_mut().get_mut(source_post_id) {
+            post_ids.retain(|id| id != remix_post_id);
+        }
+    });
+
+    POSTS.with(|posts| {
+        if let Some(source) = posts.borrow_mut().get_mut(source_post_id) {
+            source.remixes_count = source.remixes_count.saturating_sub(1);
+        }
+    });
+}
+
+pub fn get_post_remixes(source_post_id: &str) -> Vec<Post> {
+    let post_ids = POST_REMIXES.with(|remixes| remixes.borrow().get(source_post_id).cloned().unwrap_or_default());
+    POSTS.with(|posts| {
+        let posts = posts.borrow();
+        post_ids.iter().filter_map(|post_id| posts.get(post_id).cloned()).collect()
+    })
+}
+
+// Collaboration invite operations
+pub fn add_collab_invite(user_id: &str, post_id: &str) {
+    COLLAB_INVITES.with(|invites| {
+        let mut invites = invites.borrow_mut();
+        let post_ids = invites.entry(user_id.to_string()).or_default();
+        if !post_ids.iter().any(|id| id == post_id) {
+            post_ids.push(post_id.to_string());
+        }
+    });
+}
+
+pub fn remove_collab_invite(user_id: &str, post_id: &str) {
+    COLLAB_INVITES.with(|invites| {
+        if let Some(post_ids) = invites.borrow_mut().get_mut(user_id) {
+            post_ids.retain(|id| id != post_id);
+        }
+    });
+}
+
+pub fn get_collab_invites(user_id: &str) -> Vec<String> {
+    COLLAB_INVITES.with(|invites| invites.borrow().get(user_id).cloned().unwrap_or_default())
+}
+
+pub fn delete_user_collab_invites(user_id: &str) -> Result<(), String> {
+    COLLAB_INVITES.with(|invites| {
+        invites.borrow_mut().remove(user_id);
+    });
+    Ok(())
+}
+
+// Tag operations
+pub fn add_tagged_post(user_id: &str, post_id: &str) {
+    TAGGED_POSTS.with(|tagged| {
+        let mut tagged = tagged.borrow_mut();
+        let post_ids = tagged.entry(user_id.to_string()).or_default();
+        if !post_ids.iter().any(|id| id == post_id) {
+            post_ids.push(post_id.to_string());
+        }
+    });
+}
+
+pub fn remove_tagged_post(user_id: &str, post_id: &str) {
+    TAGGED_POSTS.with(|tagged| {
+        if let Some(post_ids) = tagged.borrow_mut().get_mut(user_id) {
+            post_ids.retain(|id| id != post_id);
+        }
+    });
+}
+
+pub fn get_tagged_posts(user_id: &str) -> Vec<Post> {
+    let post_ids = TAGGED_POSTS.with(|tagged| tagged.borrow().get(user_id).cloned().unwrap_or_default());
+    POSTS.with(|posts| {
+        let posts = posts.borrow();
+        post_ids.iter().filter_map(|post_id| posts.get(post_id).cloned()).collect()
+    })
+}
+
+// Removes the user's tag from a post, whether approved, pending or on media
+pub fn remove_user_tag(post_id: &str, user_id: &str) -> bool {
+    remove_tagged_post(user_id, post_id);
+
+    POSTS.with(|posts| {
+        let mut posts = posts.borrow_mut();
+        let Some(post) = posts.get_mut(post_id) else {
+            return false;
+        };
+
+        let was_tagged = post.tagged_users.iter().any(|id| id == user_id)
+            || post.pending_tagged_users.iter().any(|id| id == user_id);
+
+        post.tagged_users.retain(|id| id != user_id);
+        post.pending_tagged_users.retain(|id| id != user_id);
+        for item in &mut post.media {
+            item.user_tags.retain(|tag| tag.user_id != user_id);
+        }
+
+        was_tagged
+    })
+}
+
+// Takes the user off every post they were tagged in
+pub fn delete_user_tags(user_id: &str) -> Result<(), String> {
+    let post_ids = TAGGED_POSTS.with(|tagged| tagged.borrow_mut().remove(user_id).unwrap_or_default());
+    for post_id in post_ids {
+        remove_user_tag(&post_id, user_id);
+    }
+
+    TAG_SETTINGS.with(|settings| {
+        settings.borrow_mut().remove(user_id);
+    });
+    Ok(())
+}
+
+pub fn update_tag_settings(settings: TagSettings) {
+    TAG_SETTINGS.with(|tag_settings| {
+        tag_settings.borrow_mut().insert(settings.user_id.clone(), settings);
+    });
+}
+
+pub fn get_tag_settings(user_id: &str) -> Option<TagSettings> {
+    TAG_SETTINGS.with(|tag_settings| tag_settings.borrow().get(user_id).cloned())
+}
+
+// Sensitive content operations
+pub fn set_sensitive_content_level(user_id: String, level: SensitiveContentLevel) {
+    SENSITIVE_CONTENT_LEVELS.with(|levels| {
+        levels.borrow_mut().insert(user_id, level);
+    });
+}
+
+pub fn get_sensitive_content_level(user_id: &str) -> Option<SensitiveContentLevel> {
+    SENSITIVE_CONTENT_LEVELS.with(|levels| levels.borrow().get(user_id).cloned())
+}
+
+pub fn delete_user_sensitive_content_level(user_id: &str) -> Result<(), String> {
+    SENSITIVE_CONTENT_LEVELS.with(|levels| {
+        levels.borrow_mut().remove(user_id);
+    });
+    Ok(())
+}
+
+// Draft operations
+pub fn upsert_draft(user_id: &str, draft: PostDraft) {
+    POST_DRAFTS.with(|drafts| {
+        let mut drafts_map = drafts.borrow_mut();
+        let user_drafts = drafts_map.entry(user_id.to_string()).or_default();
+        retain_media_items(&draft.post_data.media);
+        match user_drafts.iter_mut().find(|d| d.draft_id == draft.draft_id) {
+            Some(existing) => {
+                release_media_items(&existing.post_data.media);
+                *existing = draft;
+            }
+            None => user_drafts.push(draft),
         }
+    });
+}
+
+pub fn get_draft(user_id: &str, draft_id: &str) -> Option<PostDraft> {
+    POST_DRAFTS.with(|drafts| {
+        drafts
+            .borrow()
+            .get(user_id)
+            .and_then(|user_drafts| user_drafts.iter().find(|d| d.draft_id == draft_id).cloned())
+    })
+}
+
+pub fn get_user_drafts(user_id: &str) -> Vec<PostDraft> {
+    POST_DRAFTS.with(|drafts| drafts.borrow().get(user_id).cloned().unwrap_or_default())
+}
+
+pub fn remove_draft(user_id: &str, draft_id: &str) -> bool {
+    POST_DRAFTS.with(|drafts| {
+        let mut drafts_map = drafts.borrow_mut();
+        let Some(user_drafts) = drafts_map.get_mut(user_id) else {
+            return false;
+        };
+        match user_drafts.iter().position(|d| d.draft_id == draft_id) {
+            Some(index) => {
+                let draft = user_drafts.remove(index);
+                release_media_items(&draft.post_data.media);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+pub fn delete_user_drafts(user_id: &str) -> Result<(), String> {
+    let user_drafts = POST_DRAFTS
+        .with(|drafts| drafts.borrow_mut().remove(user_id))
+        .unwrap_or_default();
+    for draft in user_drafts {
+        release_media_items(&draft.post_data.media);
+    }
+    Ok(())
+}
+
+// Scheduled content operations
+pub fn insert_scheduled_item(schedule_id: String, item: ScheduledItem) {
+    retain_media_items(scheduled_content_media(&item.content));
+    let replaced = SCHEDULED_ITEMS.with(|items| items.borrow_mut().insert(schedule_id, item));
+    if let Some(replaced) = replaced {
+        release_media_items(scheduled_content_media(&replaced.content));
+    }
+}
+
+pub fn get_scheduled_item(schedule_id: &str) -> Option<ScheduledItem> {
+    SCHEDULED_ITEMS.with(|items| items.borrow().get(&schedule_id.to_string()))
+}
+
+pub fn remove_scheduled_item(schedule_id: &str) -> Option<ScheduledItem> {
+    let removed = SCHEDULED_ITEMS.with(|items| items.borrow_mut().remove(&schedule_id.to_string()));
+    if let Some(item) = &removed {
+        release_media_items(scheduled_content_media(&item.content));
+    }
+    removed
+}
+
+pub fn get_user_scheduled_items(user_id: &str) -> Vec<ScheduledItem> {
+    SCHEDULED_ITEMS.with(|items| {
+        items
+            .borrow()
+            .iter()
+            .map(|(_, item)| item)
+            .filter(|item| item.user_id == user_id)
+            .collect()
     })
 }
 
+// Removes and returns every item whose publish time has passed, oldest first
+pub fn take_due_scheduled_items(now: u64) -> Vec<ScheduledItem> {
+    let mut due_items: Vec<ScheduledItem> = SCHEDULED_ITEMS.with(|items| {
+        items
+            .borrow()
+            .iter()
+            .map(|(_, item)| item)
+            .filter(|item| item.publish_at <= now)
+            .collect()
+    });
+
+    SCHEDULED_ITEMS.with(|items| {
+        let mut items = items.borrow_mut();
+        for item in &due_items {
+            items.remove(&item.schedule_id);
+        }
+    });
+
+    // Published content takes its own references
+    for item in &due_items {
+        release_media_items(scheduled_content_media(&item.content));
+    }
+
+    due_items.sort_by_key(|item| item.publish_at);
+    due_items
+}
+
+pub fn delete_user_scheduled_items(user_id: &str) -> Result<(), String> {
+    for item in get_user_scheduled_items(user_id) {
+        remove_scheduled_item(&item.schedule_id);
+    }
+    Ok(())
+}
+
 // Like operations
 pub fn add_post_like(post_id: String, user_id: String) -> Result<(), String> {
     // First, add the like
@@ -175,32 +1142,173 @@ pub fn remove_post_like(post_id: &str, user_id: &str) -> Result<(), String> {
     Ok(())
 }
 
-pub fn has_user_liked_post(post_id: &str, user_id: &str) -> bool {
-    POST_LIKES.with(|likes| {
+pub fn has_user_liked_post(post_id: &str, user_id: &str) -> bool {
+    POST_LIKES.with(|likes| {
+        likes
+            .borrow()
+            .get(post_id)
+            .map(|user_likes| user_likes.contains(&user_id.to_string()))
+            .unwrap_or(false)
+    })
+}
+
+// Comment operations
+pub fn insert_comment(comment_id: String, comment: Comment) {
+    let post_id = comment.post_id.clone();
+    let is_hidden = comment.is_hidden;
+
+    // First, insert the comment
+    COMMENTS.with(|comments| {
+        comments.borrow_mut().insert(comment_id, comment);
+    });
+
+    // Hidden comments are only counted once the post owner approves them
+    if !is_hidden {
+        POSTS.with(|posts| {
+            let mut posts_map = posts.borrow_mut();
+            if let Some(post) = posts_map.get_mut(&post_id) {
+                post.comments_count += 1;
+            }
+        });
+    }
+}
+
+// Hiding or unhiding a comment moves it in or out of its post and parent counts
+pub fn set_comment_hidden(comment_id: &str, hidden: bool) -> Result<Comment, String> {
+    let comment = COMMENTS.with(|comments| {
+        let mut comments_map = comments.borrow_mut();
+        let comment = comments_map
+            .get_mut(comment_id)
+            .ok_or_else(|| "Comment not found".to_string())?;
+        if comment.is_hidden == hidden {
+            return Err(if hidden {
+                "Comment is already hidden".to_string()
+            } else {
+                "Comment is not hidden".to_string()
+            });
+        }
+        comment.is_hidden = hidden;
+        Ok(comment.clone())
+    })?;
+
+    POSTS.with(|posts| {
+        if let Some(post) = posts.borrow_mut().get_mut(&comment.post_id) {
+            post.comments_count = if hidden {
+                post.comments_count.saturating_sub(1)
+            } else {
+                post.comments_count + 1
+            };
+        }
+    });
+
+    if let Some(parent_id) = &comment.parent_comment_id {
+        let _ = update_comment_reply_count(parent_id, !hidden);
+    }
+
+    Ok(comment)
+}
+
+pub fn update_comment(comment: Comment) {
+    COMMENTS.with(|comments| {
+        comments.borrow_mut().insert(comment.comment_id.clone(), comment);
+    });
+}
+
+pub fn add_comment_like(comment_id: &str, user_id: String) -> Result<Comment, String> {
+    let comment = COMMENTS.with(|comments| {
+        let mut comments_map = comments.borrow_mut();
+        let comment = comments_map
+            .get_mut(comment_id)
+            .ok_or_else(|| "Comment not found".to_string())?;
+        comment.likes_count += 1;
+        Ok::<Comment, String>(comment.clone())
+    })?;
+
+    COMMENT_LIKES.with(|likes| {
+        likes
+            .borrow_mut()
+            .entry(comment_id.to_string())
+            .or_default()
+            .push(user_id);
+    });
+
+    Ok(comment)
+}
+
+pub fn remove_comment_like(comment_id: &str, user_id: &str) -> Result<Comment, String> {
+    let like_removed = COMMENT_LIKES.with(|likes| {
+        let mut likes_map = likes.borrow_mut();
+        let Some(user_likes) = likes_map.get_mut(comment_id) else {
+            return false;
+        };
+        let initial_len = user_likes.len();
+        user_likes.retain(|id| id != user_id);
+        user_likes.len() != initial_len
+    });
+
+    if !like_removed {
+        return Err("Like not found".to_string());
+    }
+
+    COMMENTS.with(|comments| {
+        let mut comments_map = comments.borrow_mut();
+        let comment = comments_map
+            .get_mut(comment_id)
+            .ok_or_else(|| "Comment not found".to_string())?;
+        comment.likes_count = comment.likes_count.saturating_sub(1);
+        Ok(comment.clone())
+    })
+}
+
+pub fn has_user_liked_comment(comment_id: &str, user_id: &str) -> bool {
+    COMMENT_LIKES.with(|likes| {
         likes
             .borrow()
-            .get(post_id)
-            .map(|user_likes| user_likes.contains(&user_id.to_string()))
-            .unwrap_or(false)
+            .get(comment_id)
+            .is_some_and(|user_likes| user_likes.iter().any(|id| id == user_id))
     })
 }
 
-// Comment operations
-pub fn insert_comment(comment_id: String, comment: Comment) {
-    let post_id = comment.post_id.clone();
+// Removes a comment together with its replies, keeping the post and parent
+// counts in line with the visible comments that were removed
+pub fn remove_comment(comment_id: &str) -> Option<Comment> {
+    let comment = get_comment(comment_id)?;
 
-    // First, insert the comment
-    COMMENTS.with(|comments| {
-        comments.borrow_mut().insert(comment_id, comment);
+    let removed: Vec<Comment> = COMMENTS.with(|comments| {
+        let mut comments_map = comments.borrow_mut();
+        let reply_ids: Vec<String> = comments_map
+            .values()
+            .filter(|reply| reply.parent_comment_id.as_deref() == Some(comment_id))
+            .map(|reply| reply.comment_id.clone())
+            .collect();
+
+        std::iter::once(comment_id.to_string())
+            .chain(reply_ids)
+            .filter_map(|id| comments_map.remove(&id))
+            .collect()
     });
 
-    // Then update post comments count separately
+    COMMENT_LIKES.with(|likes| {
+        let mut likes_map = likes.borrow_mut();
+        for removed_comment in &removed {
+            likes_map.remove(&removed_comment.comment_id);
+        }
+    });
+
+    let visible_count = removed.iter().filter(|c| !c.is_hidden).count() as u64;
     POSTS.with(|posts| {
-        let mut posts_map = posts.borrow_mut();
-        if let Some(post) = posts_map.get_mut(&post_id) {
-            post.comments_count += 1;
+        if let Some(post) = posts.borrow_mut().get_mut(&comment.post_id) {
+            post.comments_count = post.comments_count.saturating_sub(visible_count);
         }
     });
+
+    if let Some(parent_id) = &comment.parent_comment_id {
+        if !comment.is_hidden {
+            let _ = update_comment_reply_count(parent_id, false);
+        }
+    }
+
+    Some(comment)
 }
 
 pub fn get_comment(comment_id: &str) -> Option<Comment> {
@@ -365,14 +1473,25 @@ pub fn get_conversation(conversation_id: &str) -> Option<Conversation> {
 }
 
 // Story operations
+// Updates land in whichever store currently holds the story
 pub fn insert_story(story_id: String, story: Story) {
-    STORIES.with(|stories| {
-        stories.borrow_mut().insert(story_id, story);
-    });
+    let is_archived = ARCHIVED_STORIES.with(|archived| archived.borrow().contains_key(&story_id));
+
+    if is_archived {
+        ARCHIVED_STORIES.with(|archived| {
+            archived.borrow_mut().insert(story_id, story);
+        });
+    } else {
+        STORIES.with(|stories| {
+            stories.borrow_mut().insert(story_id, story);
+        });
+    }
 }
 
 pub fn get_story(story_id: &str) -> Option<Story> {
-    STORIES.with(|stories| stories.borrow().get(story_id).cloned())
+    STORIES
+        .with(|stories| stories.borrow().get(story_id).cloned())
+        .or_else(|| ARCHIVED_STORIES.with(|archived| archived.borrow().get(story_id).cloned()))
 }
 
 pub fn get_user_stories(user_id: &str) -> Vec<Story> {
@@ -386,8 +1505,375 @@ pub fn get_user_stories(user_id: &str) -> Vec<Story> {
     })
 }
 
+// Includes expired stories the sweeper hasn't moved yet
+pub fn get_user_archived_stories(user_id: &str) -> Vec<Story> {
+    let current_time = get_current_timestamp();
+    let mut archived_stories: Vec<Story> = STORIES.with(|stories| {
+        stories
+            .borrow()
+            .values()
+            .filter(|story| story.user_id == user_id && story.expires_at <= current_time)
+            .cloned()
+            .collect()
+    });
+
+    ARCHIVED_STORIES.with(|archived| {
+        archived_stories.extend(
+            archived
+                .borrow()
+                .values()
+                .filter(|story| story.user_id == user_id)
+                .cloned(),
+        );
+    });
+
+    archived_stories
+}
+
+pub fn get_expired_story_ids(current_time: u64) -> Vec<String> {
+    STORIES.with(|stories| {
+        stories
+            .borrow()
+            .values()
+            .filter(|story| story.expires_at <= current_time)
+            .map(|story| story.story_id.clone())
+            .collect()
+    })
+}
+
+pub fn get_all_archived_stories() -> Vec<Story> {
+    ARCHIVED_STORIES.with(|archived| archived.borrow().values().cloned().collect())
+}
+
+pub fn archive_story(story_id: &str) {
+    if let Some(story) = STORIES.with(|stories| stories.borrow_mut().remove(story_id)) {
+        ARCHIVED_STORIES.with(|archived| {
+            archived.borrow_mut().insert(story_id.to_string(), story);
+        });
+    }
+}
+
+// Removes a story from either store along with everything it holds on to
+pub fn delete_story(story_id: &str) {
+    let story = STORIES
+        .with(|stories| stories.borrow_mut().remove(story_id))
+        .or_else(|| ARCHIVED_STORIES.with(|archived| archived.borrow_mut().remove(story_id)));
+
+    if let Some(story) = story {
+        STICKER_RESPONSES.with(|responses| responses.borrow_mut().remove(story_id));
+        STORY_VIEWER_HISTORY.with(|history| history.borrow_mut().remove(story_id));
+        release_media_url(&story.media.url);
+        if let Some(music_info) = &story.music_info {
+            release_audio_use(&music_info.track_id, None);
+        }
+    }
+}
+
+// Viewers who fall out of a story's capped viewer list are remembered in a
+// fixed-size bloom filter, so memory per story stays bounded however many
+// people watch it. False positives only make view_count undercount.
+const STORY_VIEWER_HISTORY_WORDS: usize = 1024; // 64 Kbit
+const STORY_VIEWER_HISTORY_HASHES: u64 = 3;
+
+fn story_viewer_history_bits(user_id: &str) -> impl Iterator<Item = usize> {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    user_id.hash(&mut hasher);
+    let first = hasher.finish();
+    first.hash(&mut hasher);
+    let second = hasher.finish() | 1;
+
+    let bit_count = (STORY_VIEWER_HISTORY_WORDS * 64) as u64;
+    (0..STORY_VIEWER_HISTORY_HASHES)
+        .map(move |index| (first.wrapping_add(index.wrapping_mul(second)) % bit_count) as usize)
+}
+
+pub fn add_story_viewer_history(story_id: &str, user_ids: &[String]) {
+    STORY_VIEWER_HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        let filter = history
+            .entry(story_id.to_string())
+            .or_insert_with(|| vec![0; STORY_VIEWER_HISTORY_WORDS]);
+        for user_id in user_ids {
+            for bit in story_viewer_history_bits(user_id) {
+                filter[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+    });
+}
+
+pub fn is_in_story_viewer_history(story_id: &str, user_id: &str) -> bool {
+    STORY_VIEWER_HISTORY.with(|history| {
+        history.borrow().get(story_id).is_some_and(|filter| {
+            story_viewer_history_bits(user_id).all(|bit| filter[bit / 64] & (1 << (bit % 64)) != 0)
+        })
+    })
+}
+
+pub fn hide_stories_from(user_id: String, hidden_user_id: String) {
+    STORY_HIDDEN_FROM.with(|hidden_from| {
+        let mut hidden_from = hidden_from.borrow_mut();
+        let hidden_ids = hidden_from.entry(user_id).or_default();
+        if !hidden_ids.contains(&hidden_user_id) {
+            hidden_ids.push(hidden_user_id);
+        }
+    });
+}
+
+pub fn unhide_stories_from(user_id: &str, hidden_user_id: &str) -> bool {
+    STORY_HIDDEN_FROM.with(|hidden_from| {
+        hidden_from.borrow_mut().get_mut(user_id).is_some_and(|hidden_ids| {
+            let initial_len = hidden_ids.len();
+            hidden_ids.retain(|id| id != hidden_user_id);
+            hidden_ids.len() < initial_len
+        })
+    })
+}
+
+pub fn get_story_hidden_from(user_id: &str) -> Vec<String> {
+    STORY_HIDDEN_FROM.with(|hidden_from| hidden_from.borrow().get(user_id).cloned().unwrap_or_default())
+}
+
+pub fn is_story_hidden_from(user_id: &str, viewer_id: &str) -> bool {
+    STORY_HIDDEN_FROM.with(|hidden_from| {
+        hidden_from
+            .borrow()
+            .get(user_id)
+            .is_some_and(|hidden_ids| hidden_ids.iter().any(|id| id == viewer_id))
+    })
+}
+
+pub fn set_story_reply_setting(user_id: String, setting: StoryReplySetting) {
+    STORY_REPLY_SETTINGS.with(|reply_settings| {
+        reply_settings.borrow_mut().insert(user_id, setting);
+    });
+}
+
+pub fn get_story_reply_setting(user_id: &str) -> StoryReplySetting {
+    STORY_REPLY_SETTINGS.with(|reply_settings| {
+        reply_settings
+            .borrow()
+            .get(user_id)
+            .cloned()
+            .unwrap_or(StoryReplySetting::Everyone)
+    })
+}
+
+pub fn update_story_archive_settings(settings: StoryArchiveSettings) {
+    STORY_ARCHIVE_SETTINGS.with(|archive_settings| {
+        archive_settings.borrow_mut().insert(settings.user_id.clone(), settings);
+    });
+}
+
+pub fn get_story_archive_settings(user_id: &str) -> Option<StoryArchiveSettings> {
+    STORY_ARCHIVE_SETTINGS.with(|archive_settings| archive_settings.borrow().get(user_id).cloned())
+}
+
+// Sticker response operations
+pub fn add_sticker_response(response: StickerResponse) {
+    STICKER_RESPONSES.with(|responses| {
+        responses
+            .borrow_mut()
+            .entry(response.story_id.clone())
+            .or_default()
+            .push(response);
+    });
+}
+
+pub fn get_sticker_responses(story_id: &str) -> Vec<StickerResponse> {
+    STICKER_RESPONSES.with(|responses| responses.borrow().get(story_id).cloned().unwrap_or_default())
+}
+
+pub fn has_user_responded_to_sticker(story_id: &str, sticker_index: u32, user_id: &str) -> bool {
+    STICKER_RESPONSES.with(|responses| {
+        responses.borrow().get(story_id).is_some_and(|story_responses| {
+            story_responses
+                .iter()
+                .any(|response| response.sticker_index == sticker_index && response.user_id == user_id)
+        })
+    })
+}
+
+// Live stream operations
+pub fn insert_live_stream(stream: LiveStream) {
+    LIVE_STREAMS.with(|streams| {
+        streams.borrow_mut().insert(stream.stream_id.clone(), stream);
+    });
+}
+
+pub fn get_live_stream(stream_id: &str) -> Option<LiveStream> {
+    LIVE_STREAMS.with(|streams| streams.borrow().get(stream_id).cloned())
+}
+
+pub fn get_active_live_streams() -> Vec<LiveStream> {
+    LIVE_STREAMS.with(|streams| {
+        streams
+            .borrow()
+            .values()
+            .filter(|stream| stream.is_active)
+            .cloned()
+            .collect()
+    })
+}
+
+pub fn delete_user_live_streams(user_id: &str) -> Result<(), String> {
+    LIVE_STREAMS.with(|streams| {
+        let mut streams_map = streams.borrow_mut();
+        streams_map.retain(|_, stream| stream.user_id != user_id);
+
+        // Drop the user from streams they were watching or moderating
+        for stream in streams_map.values_mut() {
+            stream.viewers.retain(|viewer| viewer.user_id != user_id);
+            stream.moderators.retain(|id| id != user_id);
+            stream.viewers_count = stream.viewers.len() as u64;
+        }
+    });
+    Ok(())
+}
+
+// Highlight operations
+pub fn insert_highlight(highlight_id: String, highlight: Highlight) {
+    let user_id = highlight.user_id.clone();
+    HIGHLIGHTS.with(|highlights| {
+        highlights.borrow_mut().insert(highlight_id.clone(), highlight);
+    });
+
+    // Add to user's highlights if this is a new highlight
+    USER_HIGHLIGHTS.with(|user_highlights| {
+        let mut user_highlights = user_highlights.borrow_mut();
+        let highlight_ids = user_highlights.entry(user_id).or_insert_with(Vec::new);
+        if !highlight_ids.contains(&highlight_id) {
+            highlight_ids.push(highlight_id);
+        }
+    });
+}
+
+pub fn get_highlight(highlight_id: &str) -> Option<Highlight> {
+    HIGHLIGHTS.with(|highlights| highlights.borrow().get(highlight_id).cloned())
+}
+
+pub fn get_user_highlights(user_id: &str) -> Vec<Highlight> {
+    USER_HIGHLIGHTS.with(|user_highlights| {
+        if let Some(highlight_ids) = user_highlights.borrow().get(user_id) {
+            HIGHLIGHTS.with(|highlights| {
+                highlight_ids
+                    .iter()
+                    .filter_map(|highlight_id| highlights.borrow().get(highlight_id).cloned())
+                    .collect()
+            })
+        } else {
+            Vec::new()
+        }
+    })
+}
+
+pub fn set_user_highlight_order(user_id: String, highlight_ids: Vec<String>) {
+    USER_HIGHLIGHTS.with(|user_highlights| {
+        user_highlights.borrow_mut().insert(user_id, highlight_ids);
+    });
+}
+
+pub fn delete_highlight(highlight_id: &str) -> Result<(), String> {
+    let highlight = HIGHLIGHTS
+        .with(|highlights| highlights.borrow_mut().remove(highlight_id))
+        .ok_or_else(|| "Highlight not found".to_string())?;
+
+    USER_HIGHLIGHTS.with(|user_highlights| {
+        if let Some(highlight_ids) = user_highlights.borrow_mut().get_mut(&highlight.user_id) {
+            highlight_ids.retain(|id| id != highlight_id);
+        }
+    });
+
+    // Release the stories so they can be added to another highlight
+    for story_id in &highlight.stories {
+        if let Some(mut story) = get_story(story_id) {
+            story.is_highlight = false;
+            story.highlight_id = None;
+            insert_story(story_id.clone(), story);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn delete_user_highlights(user_id: &str) -> Result<(), String> {
+    let highlight_ids = USER_HIGHLIGHTS
+        .with(|user_highlights| user_highlights.borrow_mut().remove(user_id))
+        .unwrap_or_default();
+
+    HIGHLIGHTS.with(|highlights| {
+        let mut highlights_map = highlights.borrow_mut();
+        for highlight_id in highlight_ids {
+            highlights_map.remove(&highlight_id);
+        }
+    });
+
+    Ok(())
+}
+
+// Audio operations
+pub fn insert_audio_track(track: AudioTrack) {
+    AUDIO_TRACKS.with(|tracks| {
+        tracks.borrow_mut().insert(track.track_id.clone(), track);
+    });
+}
+
+pub fn get_audio_track(track_id: &str) -> Option<AudioTrack> {
+    AUDIO_TRACKS.with(|tracks| tracks.borrow().get(track_id).cloned())
+}
+
+pub fn get_all_audio_tracks() -> Vec<AudioTrack> {
+    AUDIO_TRACKS.with(|tracks| tracks.borrow().values().cloned().collect())
+}
+
+pub fn add_audio_post(track_id: &str, post_id: &str) {
+    AUDIO_POSTS.with(|audio_posts| {
+        let mut audio_posts = audio_posts.borrow_mut();
+        let post_ids = audio_posts.entry(track_id.to_string()).or_default();
+        if !post_ids.iter().any(|id| id == post_id) {
+            post_ids.push(post_id.to_string());
+        }
+    });
+}
+
+pub fn get_audio_posts(track_id: &str) -> Vec<Post> {
+    let post_ids = AUDIO_POSTS.with(|audio_posts| audio_posts.borrow().get(track_id).cloned().unwrap_or_default());
+    POSTS.with(|posts| {
+        let posts = posts.borrow();
+        post_ids.iter().filter_map(|post_id| posts.get(post_id).cloned()).collect()
+    })
+}
+
+// Drops one use of the track; post_id is None for stories, which aren't indexed
+pub fn release_audio_use(track_id: &str, post_id: Option<&str>) {
+    AUDIO_TRACKS.with(|tracks| {
+        if let Some(track) = tracks.borrow_mut().get_mut(track_id) {
+            track.usage_count = track.usage_count.saturating_sub(1);
+        }
+    });
+
+    if let Some(post_id) = post_id {
+        AUDIO_POSTS.with(|audio_posts| {
+            if let Some(post_ids) = audio_posts.borrow_mut().get_mut(track_id) {
+                post_ids.retain(|id| id != post_id);
+            }
+        });
+    }
+}
+
 // Hashtag operations
+// Index keys ignore case and a leading '#', so #Travel and travel share a page
+pub fn normalize_hashtag(hashtag: &str) -> String {
+    hashtag.trim().trim_start_matches('#').to_lowercase()
+}
+
 pub fn insert_hashtag(hashtag: String, post_id: String) {
+    let hashtag = normalize_hashtag(&hashtag);
+    if hashtag.is_empty() {
+        return;
+    }
+
     HASHTAGS.with(|hashtags| {
         hashtags
             .borrow_mut()
@@ -399,23 +1885,104 @@ pub fn insert_hashtag(hashtag: String, post_id: String) {
             });
     });
 
-    HASHTAG_POSTS.with(|hashtag_posts| {
-        hashtag_posts
-            .borrow_mut()
-            .entry(hashtag.clone())
-            .or_insert_with(Vec::new)
-            .push(post_id);
+    let inserted = HASHTAG_POSTS.with(|hashtag_posts| {
+        let mut hashtag_posts = hashtag_posts.borrow_mut();
+        let post_ids = hashtag_posts.entry(hashtag.clone()).or_default();
+        if post_ids.contains(&post_id) {
+            return false;
+        }
+        post_ids.push(post_id);
+        true
+    });
+
+    // Update hashtag posts count
+    if inserted {
+        HASHTAGS.with(|hashtags| {
+            if let Some(hashtag_data) = hashtags.borrow_mut().get_mut(&hashtag) {
+                hashtag_data.posts_count += 1;
+            }
+        });
+    }
+}
+
+pub fn remove_hashtag(hashtag: &str, post_id: &str) {
+    let hashtag = normalize_hashtag(hashtag);
+    let removed = HASHTAG_POSTS.with(|hashtag_posts| {
+        if let Some(post_ids) = hashtag_posts.borrow_mut().get_mut(&hashtag) {
+            let initial_len = post_ids.len();
+            post_ids.retain(|id| id != post_id);
+            initial_len - post_ids.len()
+        } else {
+            0
+        }
     });
 
     // Update hashtag posts count
     HASHTAGS.with(|hashtags| {
-        if let Some(mut hashtag_data) = hashtags.borrow().get(&hashtag).cloned() {
-            hashtag_data.posts_count += 1;
-            hashtags.borrow_mut().insert(hashtag, hashtag_data);
+        if let Some(hashtag_data) = hashtags.borrow_mut().get_mut(&hashtag) {
+            hashtag_data.posts_count = hashtag_data.posts_count.saturating_sub(removed as u64);
+        }
+    });
+}
+
+pub fn get_hashtag(hashtag: &str) -> Option<Hashtag> {
+    HASHTAGS.with(|hashtags| hashtags.borrow().get(&normalize_hashtag(hashtag)).cloned())
+}
+
+// Walks the index from its newest entries and stops once `limit` posts pass
+// the filter, so hot hashtags aren't loaded in full
+pub fn get_recent_hashtag_posts(hashtag: &str, limit: usize, filter: impl Fn(&Post) -> bool) -> Vec<Post> {
+    HASHTAG_POSTS.with(|hashtag_posts| {
+        let hashtag_posts = hashtag_posts.borrow();
+        let Some(post_ids) = hashtag_posts.get(&normalize_hashtag(hashtag)) else {
+            return Vec::new();
+        };
+
+        POSTS.with(|posts| {
+            let posts = posts.borrow();
+            post_ids
+                .iter()
+                .rev()
+                .filter_map(|post_id| posts.get(post_id))
+                .filter(|post| filter(post))
+                .take(limit)
+                .cloned()
+                .collect()
+        })
+    })
+}
+
+pub fn follow_hashtag(user_id: String, hashtag: String) {
+    FOLLOWED_HASHTAGS.with(|followed| {
+        let mut followed = followed.borrow_mut();
+        let hashtags = followed.entry(user_id).or_default();
+        if !hashtags.contains(&hashtag) {
+            hashtags.push(hashtag);
         }
     });
 }
 
+pub fn unfollow_hashtag(user_id: &str, hashtag: &str) -> bool {
+    FOLLOWED_HASHTAGS.with(|followed| {
+        followed.borrow_mut().get_mut(user_id).is_some_and(|hashtags| {
+            let initial_len = hashtags.len();
+            hashtags.retain(|name| name != hashtag);
+            hashtags.len() < initial_len
+        })
+    })
+}
+
+pub fn get_followed_hashtags(user_id: &str) -> Vec<String> {
+    FOLLOWED_HASHTAGS.with(|followed| followed.borrow().get(user_id).cloned().unwrap_or_default())
+}
+
+pub fn delete_user_followed_hashtags(user_id: &str) -> Result<(), String> {
+    FOLLOWED_HASHTAGS.with(|followed| {
+        followed.borrow_mut().remove(user_id);
+    });
+    Ok(())
+}
+
 pub fn search_hashtags(query: &str) -> Vec<Hashtag> {
     HASHTAGS.with(|hashtags| {
         hashtags
@@ -478,8 +2045,14 @@ pub fn restore_state() {
 pub fn generate_id() -> String {
     use ic_cdk::api::time;
 
-    // Use IC time as seed for deterministic randomness
-    let mut seed = time();
+    // Use IC time as seed for deterministic randomness. The counter keeps IDs
+    // generated within the same message (same time()) distinct.
+    let counter = ID_COUNTER.with(|counter| {
+        let mut counter = counter.borrow_mut();
+        *counter = counter.wrapping_add(1);
+        *counter
+    });
+    let mut seed = time().wrapping_add(counter.wrapping_mul(0x9E37_79B9_7F4A_7C15));
     let chars = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
     let mut result = String::new();
 
@@ -998,12 +2571,9 @@ pub fn delete_comments_by_user(user_id: &str) -> Result<(), String> {
             .collect()
     });
 
-    COMMENTS.with(|comments| {
-        let mut comments_map = comments.borrow_mut();
-        for comment_id in comment_ids {
-            comments_map.remove(&comment_id);
-        }
-    });
+    for comment_id in comment_ids {
+        remove_comment(&comment_id);
+    }
 
     Ok(())
 }
@@ -1058,7 +2628,11 @@ pub fn delete_user_messages(user_id: &str) -> Result<(), String> {
     MESSAGES.with(|messages| {
         let mut messages_map = messages.borrow_mut();
         for conversation_id in &user_conversations {
-            messages_map.remove(conversation_id);
+            for message in messages_map.remove(conversation_id).unwrap_or_default() {
+                if let Some(media_url) = &message.media_url {
+                    release_media_url(media_url);
+                }
+            }
         }
     });
 
@@ -1088,19 +2662,44 @@ pub fn delete_user_messages(user_id: &str) -> Result<(), String> {
 }
 
 pub fn delete_user_stories(user_id: &str) -> Result<(), String> {
-    let story_ids: Vec<String> = STORIES.with(|stories| {
+    // Drop the user's responses to other people's stickers
+    STICKER_RESPONSES.with(|responses| {
+        for story_responses in responses.borrow_mut().values_mut() {
+            story_responses.retain(|response| response.user_id != user_id);
+        }
+    });
+
+    let mut story_ids: Vec<String> = STORIES.with(|stories| {
         stories
             .borrow()
-            .iter()
-            .filter(|(_, story)| story.user_id == user_id)
-            .map(|(story_id, _)| story_id.clone())
+            .values()
+            .filter(|story| story.user_id == user_id)
+            .map(|story| story.story_id.clone())
             .collect()
     });
 
-    STORIES.with(|stories| {
-        let mut stories_map = stories.borrow_mut();
-        for story_id in story_ids {
-            stories_map.remove(&story_id);
+    ARCHIVED_STORIES.with(|archived| {
+        story_ids.extend(
+            archived
+                .borrow()
+                .values()
+                .filter(|story| story.user_id == user_id)
+                .map(|story| story.story_id.clone()),
+        );
+    });
+
+    for story_id in story_ids {
+        delete_story(&story_id);
+    }
+
+    STORY_ARCHIVE_SETTINGS.with(|archive_settings| archive_settings.borrow_mut().remove(user_id));
+    STORY_REPLY_SETTINGS.with(|reply_settings| reply_settings.borrow_mut().remove(user_id));
+
+    STORY_HIDDEN_FROM.with(|hidden_from| {
+        let mut hidden_from = hidden_from.borrow_mut();
+        hidden_from.remove(user_id);
+        for hidden_ids in hidden_from.values_mut() {
+            hidden_ids.retain(|id| id != user_id);
         }
     });
 
@@ -1403,10 +3002,14 @@ pub fn remove_message_reaction(conversation_id: &str, message_id: &str, user_id:
 pub fn delete_message(conversation_id: &str, message_id: &str) -> Result<(), String> {
     MESSAGES.with(|messages| {
         if let Some(conversation_messages) = messages.borrow_mut().get_mut(conversation_id) {
-            let initial_len = conversation_messages.len();
-            conversation_messages.retain(|m| m.message_id != message_id);
+            let position = conversation_messages.iter().position(|m| m.message_id == message_id);
             
-            if conversation_messages.len() < initial_len {
+            if let Some(index) = position {
+                let removed = conversation_messages.remove(index);
+                if let Some(media_url) = &removed.media_url {
+                    release_media_url(media_url);
+                }
+                
                 // Update conversation's last message if the deleted message was the last one
                 CONVERSATIONS.with(|conversations| {
                     if let Some(conversation) = conversations.borrow_mut().get_mut(conversation_id) {
@@ -25,6 +25,7 @@ pub struct UserProfile {
     pub location: Option<String>,
     pub date_of_birth: Option<u64>,
     pub gender: Option<String>,
+    pub business_profile: Option<BusinessProfile>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -34,6 +35,74 @@ pub enum AccountType {
     Business,
 }
 
+// Business Profile Types (Business and Creator accounts)
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BusinessProfile {
+    pub category: Option<ProfileCategory>,
+    pub contact_email: Option<String>,
+    pub contact_phone: Option<String>,
+    pub address: Option<BusinessAddress>,
+    pub action_buttons: Vec<ActionButton>,
+    pub show_contact_options: bool,
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ProfileCategory {
+    Artist,
+    Musician,
+    Writer,
+    Photographer,
+    DigitalCreator,
+    PublicFigure,
+    Athlete,
+    Blogger,
+    Education,
+    Entertainment,
+    Restaurant,
+    Cafe,
+    Retail,
+    HealthBeauty,
+    Fitness,
+    Travel,
+    LocalBusiness,
+    ProfessionalServices,
+    Nonprofit,
+    Other,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BusinessAddress {
+    pub street: String,
+    pub city: String,
+    pub postal_code: Option<String>,
+    pub country: String,
+    pub location: Option<LocationTag>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ActionButton {
+    pub button_type: ActionButtonType,
+    pub url: String,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ActionButtonType {
+    Book,
+    Order,
+    Reserve,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct UpdateBusinessProfileRequest {
+    pub category: Option<ProfileCategory>,
+    pub contact_email: Option<String>,
+    pub contact_phone: Option<String>,
+    pub address: Option<BusinessAddress>,
+    pub action_buttons: Option<Vec<ActionButton>>,
+    pub show_contact_options: Option<bool>,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct CreateUserProfileRequest {
     pub username: String,
@@ -68,10 +137,11 @@ pub struct Post {
     pub user_id: String,
     pub username: String,
     pub content_type: ContentType,
-    pub media_urls: Vec<String>,
+    pub media: Vec<MediaItem>,
     pub caption: String,
     pub hashtags: Vec<String>,
-    pub tagged_users: Vec<String>,
+    pub tagged_users: Vec<String>,         // approved tags, including people tagged on media
+    pub pending_tagged_users: Vec<String>, // tags waiting for the tagged user's approval
     pub location: Option<LocationTag>,
     pub likes_count: u64,
     pub comments_count: u64,
@@ -79,12 +149,94 @@ pub struct Post {
     pub created_at: u64,
     pub updated_at: u64,
     pub is_archived: bool,
+    pub is_pinned: bool,
+    pub is_edited: bool,
     pub visibility: PostVisibility,
     pub music_info: Option<MusicInfo>,
     pub product_tags: Vec<ProductTag>,
+    pub collaborators: Vec<PostCollaborator>,
+    pub remix_of: Option<RemixAttribution>,
+    pub allow_remixes: bool,
+    pub remixes_count: u64,
+    pub hide_like_count: bool, // for viewers other than the authors, also set when the count was masked
+    pub content_warning: Option<ContentWarning>,
+    pub is_blurred: bool, // set in responses when the viewer should see the post behind a warning
+    pub poll: Option<PostPoll>,
     pub(crate) post_type: (),
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PostPoll {
+    pub options: Vec<PollOption>,
+    pub total_votes: u64,
+    pub ends_at: u64,
+    pub is_closed: bool,
+    pub viewer_vote: Option<u32>, // option index the caller voted for, set in responses
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PollOption {
+    pub text: String,
+    pub votes_count: u64,
+    pub percentage: f32, // share of total_votes, set in responses
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CreatePollRequest {
+    pub options: Vec<String>,
+    pub ends_at: u64,
+}
+
+impl Post {
+    // The owner plus every co-author who accepted the invitation
+    pub fn is_author(&self, user_id: &str) -> bool {
+        self.user_id == user_id
+            || self.collaborators.iter().any(|collaborator| {
+                collaborator.user_id == user_id && collaborator.status == CollaboratorStatus::Accepted
+            })
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PostCollaborator {
+    pub user_id: String,
+    pub username: String,
+    pub status: CollaboratorStatus,
+    pub invited_at: u64,
+    pub responded_at: Option<u64>,
+}
+
+// Credits the source reel on a remix or duet
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RemixAttribution {
+    pub source_post_id: String,
+    pub source_user_id: String,
+    pub source_username: String,
+    pub remix_type: RemixType,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum RemixType {
+    Remix,
+    Duet,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CollaboratorStatus {
+    Pending,
+    Accepted,
+}
+
+// Snapshot of a post's caption and hashtags as they were before an edit
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PostRevision {
+    pub post_id: String,
+    pub revision_number: u32,
+    pub caption: String,
+    pub hashtags: Vec<String>,
+    pub edited_at: u64,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub enum ContentType {
     Photo,
@@ -95,6 +247,81 @@ pub enum ContentType {
     Live,
 }
 
+// A single photo or video; a Carousel holds one per slide
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MediaItem {
+    pub url: String,
+    pub media_type: MediaType,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub alt_text: Option<String>,
+    pub user_tags: Vec<UserTag>,
+    pub product_tags: Vec<ProductTag>,
+}
+
+impl MediaItem {
+    // Builds an item from a bare URL, as stored before media metadata existed
+    pub fn from_url(url: String) -> Self {
+        let lower_url = url.to_lowercase();
+        let is_video = [".mp4", ".mov", ".webm", ".m4v"]
+            .iter()
+            .any(|extension| lower_url.split('?').next().unwrap_or("").ends_with(extension));
+
+        Self {
+            url,
+            media_type: if is_video { MediaType::Video } else { MediaType::Image },
+            width: None,
+            height: None,
+            alt_text: None,
+            user_tags: Vec::new(),
+            product_tags: Vec::new(),
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MediaType {
+    Image,
+    Video,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct UserTag {
+    pub user_id: String,
+    pub x_position: f32,
+    pub y_position: f32,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ContentWarning {
+    Sensitive,
+    Violence,
+    Nudity,
+    Graphic,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SensitiveContentLevel {
+    Allow,
+    Limit,     // hides violence, nudity and graphic content from discovery
+    LimitMore, // hides everything with a content warning from discovery
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TagPermission {
+    Everyone,
+    PeopleYouFollow,
+    Nobody,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TagSettings {
+    pub user_id: String,
+    pub allow_tags_from: TagPermission,
+    pub manually_approve_tags: bool,
+    pub updated_at: u64,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub enum PostVisibility {
     Public,
@@ -106,7 +333,7 @@ pub enum PostVisibility {
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct CreatePostRequest {
     pub content_type: ContentType,
-    pub media_urls: Vec<String>,
+    pub media: Vec<MediaItem>,
     pub caption: String,
     pub hashtags: Vec<String>,
     pub tagged_users: Vec<String>,
@@ -114,6 +341,23 @@ pub struct CreatePostRequest {
     pub visibility: PostVisibility,
     pub music_info: Option<MusicInfo>,
     pub product_tags: Vec<ProductTag>,
+    pub collaborators: Option<Vec<String>>, // user_ids invited to co-author the post
+    pub remix_of: Option<String>,           // source reel post_id
+    pub remix_type: Option<RemixType>,      // defaults to Remix
+    pub allow_remixes: Option<bool>,        // defaults to true
+    pub hide_like_count: Option<bool>,
+    pub content_warning: Option<ContentWarning>,
+    pub poll: Option<CreatePollRequest>,
+}
+
+// Unpublished post kept server-side so it can be resumed on any device
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PostDraft {
+    pub draft_id: String,
+    pub user_id: String,
+    pub post_data: CreatePostRequest,
+    pub created_at: u64,
+    pub updated_at: u64,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -144,22 +388,104 @@ pub struct ProductTag {
     pub y_position: f32,
 }
 
+// Scheduling Types
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum ScheduledContent {
+    Post(CreatePostRequest),
+    Story(CreateStoryRequest),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduledItem {
+    pub schedule_id: String,
+    pub user_id: String,
+    pub content: ScheduledContent,
+    pub publish_at: u64,
+    pub created_at: u64,
+}
+
+// Media Storage Types
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MediaUpload {
+    pub upload_id: String,
+    pub user_id: String,
+    pub mime_type: String,
+    pub total_size: u64,
+    pub chunk_size: u64,
+    pub chunk_count: u32,
+    pub created_at: u64,
+}
+
+// A content-addressed blob; `url` is what posts, stories and messages reference
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StoredMedia {
+    pub hash: String,
+    pub url: String,
+    pub mime_type: String,
+    pub size: u64,
+    pub chunk_count: u32,
+    pub ref_count: u64,
+    pub owners: Vec<String>,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MediaStorageUsage {
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+}
+
 // Story Types
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct Story {
     pub story_id: String,
     pub user_id: String,
     pub username: String,
-    pub media_url: String,
+    pub media: MediaItem,
     pub story_type: StoryType,
     pub text_overlay: Option<String>,
     pub stickers: Vec<Sticker>,
     pub music_info: Option<MusicInfo>,
-    pub viewers: Vec<String>,
+    pub viewers: Vec<String>, // most recent viewers, capped
+    pub view_count: u64,
     pub created_at: u64,
     pub expires_at: u64,
     pub is_highlight: bool,
     pub highlight_id: Option<String>,
+    pub audience: StoryAudience,
+    pub content_warning: Option<ContentWarning>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum StoryAudience {
+    Everyone,
+    CloseFriends,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum StoryReplySetting {
+    Everyone,
+    Followers, // people who follow the story owner
+    Following, // people the story owner follows
+    Off,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StoryArchiveSettings {
+    pub user_id: String,
+    pub retention_days: u32, // 0 turns the archive off
+    pub updated_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CreateStoryRequest {
+    pub media: MediaItem,
+    pub story_type: StoryType,
+    pub text_overlay: Option<String>,
+    pub stickers: Vec<Sticker>,
+    pub music_info: Option<MusicInfo>,
+    pub audience: Option<StoryAudience>, // defaults to Everyone
+    pub content_warning: Option<ContentWarning>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -178,6 +504,7 @@ pub struct Sticker {
     pub y_position: f32,
     pub rotation: f32,
     pub scale: f32,
+    pub payload: Option<StickerPayload>, // required for interactive sticker types
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -195,6 +522,47 @@ pub enum StickerType {
     AddYours,
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum StickerPayload {
+    Poll { question: String, options: Vec<String> },
+    Question { prompt: String },
+    Quiz { question: String, options: Vec<String>, correct_option: u32 },
+    Slider { question: String, emoji: String },
+    Countdown { title: String, ends_at: u64 },
+    AddYours { prompt: String },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum StickerResponseValue {
+    Vote(u32), // poll or quiz option index
+    Answer(String),
+    Slide(f32), // 0.0 to 1.0
+    Subscribe,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StickerResponse {
+    pub story_id: String,
+    pub sticker_index: u32,
+    pub user_id: String,
+    pub username: String,
+    pub value: StickerResponseValue,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StickerResults {
+    pub sticker_index: u32,
+    pub sticker_type: StickerType,
+    pub response_count: u64,
+    pub vote_counts: Vec<u64>, // per option, for polls and quizzes
+    pub vote_percentages: Vec<f32>,
+    pub correct_count: Option<u64>, // quizzes only
+    pub average_slider_value: Option<f32>,
+    pub answers: Vec<StickerResponse>, // questions and add-yours
+    pub subscriber_count: u64, // countdowns only
+}
+
 // Comment Types
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct Comment {
@@ -210,6 +578,14 @@ pub struct Comment {
     pub updated_at: u64,
     pub is_pinned: bool,
     pub mentions: Vec<String>,
+    pub is_hidden: bool,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum CommentSortMode {
+    Top,
+    Newest,
+    Following,
 }
 
 // Message Types
@@ -228,6 +604,16 @@ pub struct Message {
     pub is_vanish_mode: bool,
     pub created_at: u64,
     pub expires_at: Option<u64>,
+    pub story: Option<StoryReference>, // set on story replies and reactions
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StoryReference {
+    pub story_id: String,
+    pub story_owner_id: String,
+    pub preview_url: Option<String>, // cleared once the story expires
+    pub story_expires_at: u64,
+    pub is_reaction: bool,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -288,6 +674,7 @@ pub enum NotificationType {
     Follow,
     Mention,
     Tag,
+    Remix,
     Story,
     Live,
     Message,
@@ -295,6 +682,25 @@ pub enum NotificationType {
     System,
 }
 
+// Audio Types
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct AudioTrack {
+    pub track_id: String,
+    pub title: String,
+    pub artist: String,
+    pub duration: u32,
+    pub original_post_id: Option<String>, // first post that used the track
+    pub usage_count: u64,
+    pub created_at: u64,
+}
+
+// "Use this audio" page: the track plus reels that use it
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct AudioPage {
+    pub track: AudioTrack,
+    pub reels: Vec<Post>,
+}
+
 // Search Types
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct SearchResults {
@@ -302,6 +708,7 @@ pub struct SearchResults {
     pub posts: Vec<Post>,
     pub hashtags: Vec<Hashtag>,
     pub locations: Vec<LocationTag>,
+    pub audio_tracks: Vec<AudioTrack>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -321,6 +728,20 @@ pub struct Hashtag {
     pub is_trending: bool,
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum HashtagTab {
+    Top,
+    Recent,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct HashtagPage {
+    pub hashtag: Hashtag,
+    pub posts: Vec<Post>,
+    pub related_hashtags: Vec<Hashtag>,
+    pub is_following: bool,
+}
+
 // Relationship Types
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct Relationship {
@@ -393,11 +814,23 @@ pub struct LiveStream {
     pub stream_id: String,
     pub user_id: String,
     pub title: String,
-    pub viewers_count: u64,
+    pub viewers_count: u64, // current concurrent viewers
+    pub peak_viewers_count: u64,
+    pub viewers: Vec<LiveViewer>,          // host and moderators only
+    pub moderators: Vec<String>,           // host and moderators only
+    pub removed_viewers: Vec<String>,      // can't rejoin this stream; host and moderators only
     pub comments: Vec<LiveComment>,
+    pub next_comment_sequence: u64,
     pub is_active: bool,
     pub started_at: u64,
     pub ended_at: Option<u64>,
+    pub saved_post_id: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct LiveViewer {
+    pub user_id: String,
+    pub last_seen: u64, // refreshed by joining and by heartbeats
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -406,6 +839,7 @@ pub struct LiveComment {
     pub user_id: String,
     pub username: String,
     pub content: String,
+    pub sequence: u64, // poll cursor, increasing per stream
     pub created_at: u64,
 }
 
@@ -515,6 +949,7 @@ pub struct NotificationSettings {
     pub messages_enabled: bool,
     pub story_views_enabled: bool,
     pub tags_enabled: bool,
+    pub remixes_enabled: bool,
     pub push_notifications: bool,
     pub email_notifications: bool,
 }
@@ -541,3 +976,44 @@ pub struct DailyActivity {
     pub stories: u32,
     pub interactions: u32,
 }
+
+// HTTP Gateway Types
+pub type HeaderField = (String, String);
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<HeaderField>,
+    pub body: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<HeaderField>,
+    pub body: Vec<u8>,
+    pub streaming_strategy: Option<StreamingStrategy>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StreamingCallbackToken {
+    pub hash: String,
+    pub chunk_index: u32,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct StreamingCallbackHttpResponse {
+    pub body: Vec<u8>,
+    pub token: Option<StreamingCallbackToken>,
+}
+
+candid::define_function!(pub StreamingCallback : (StreamingCallbackToken) -> (StreamingCallbackHttpResponse) query);
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum StreamingStrategy {
+    Callback {
+        callback: StreamingCallback,
+        token: StreamingCallbackToken,
+    },
+}
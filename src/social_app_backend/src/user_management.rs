@@ -39,6 +39,7 @@ pub fn register_user(user_id: String) -> Result<(), String> {
             location: None,
             date_of_birth: None,
             gender: None,
+            business_profile: None,
         };
 
         state_handler::insert_user(user_id, default_profile);
@@ -275,6 +276,10 @@ pub fn is_user_restricted(user_id: &str, target_user_id: &str) -> bool {
     state_handler::is_user_restricted(user_id, target_user_id)
 }
 
+pub fn is_moderator(user_id: &str) -> bool {
+    crate::is_admin(user_id)
+}
+
 pub fn get_blocked_users(user_id: &str) -> Vec<String> {
     state_handler::get_blocked_users_list(user_id)
 }
@@ -389,6 +394,7 @@ pub fn create_multiple_profiles(count: u32) -> Result<Vec<UserProfile>, String>
             },
             date_of_birth: None,
             gender: None,
+            business_profile: None,
         };
 
         state_handler::insert_user(user_id, user_profile.clone());